@@ -0,0 +1,344 @@
+use async_graphql::{ComplexObject, Context, Enum, Json, SimpleObject, ID};
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use mongodb::{ClientSession, Collection, Database};
+use serde::{Deserialize, Serialize};
+
+use crate::base::auth::CurrentUser;
+use crate::base::base_model::Status;
+use crate::base::error::ServiceResult;
+use crate::base::scalars::DateTime;
+use crate::constants::audit::{
+    AUDIT_COLLECTION, AUDIT_IGNORED_FIELDS, AUDIT_REDACTED, AUDIT_REDACTED_FIELDS, REQUEST_ID_MAX_LENGTH,
+};
+use crate::db::indexes::{IndexSpec, ModelIndexes};
+
+#[derive(Enum, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOperation {
+    Create,
+    Update,
+    SoftDelete,
+    Restore,
+    Delete,
+}
+
+impl AuditOperation {
+    // Status writes are reported as the lifecycle step they perform
+    pub fn for_status(status: Status, before: Option<&Document>) -> Self {
+        let was_deleted = before
+            .and_then(|document| document.get_i32("status").ok())
+            .is_some_and(|code| code == Status::Deleted.code());
+        match status {
+            Status::Deleted => AuditOperation::SoftDelete,
+            _ if was_deleted => AuditOperation::Restore,
+            _ => AuditOperation::Update,
+        }
+    }
+}
+
+// One top-level field whose value differs between the two snapshots
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+#[graphql(complex)]
+pub struct FieldChange {
+    pub field: String,
+    #[graphql(skip)]
+    pub before: Option<Bson>,
+    #[graphql(skip)]
+    pub after: Option<Bson>,
+}
+
+#[ComplexObject]
+impl FieldChange {
+    async fn before(&self) -> Option<Json<serde_json::Value>> {
+        self.before.clone().map(|value| Json(value.into_relaxed_extjson()))
+    }
+
+    async fn after(&self) -> Option<Json<serde_json::Value>> {
+        self.after.clone().map(|value| Json(value.into_relaxed_extjson()))
+    }
+}
+
+// Entry of the `audit_logs` collection. Snapshots are the raw documents, with
+// sensitive fields redacted, so they also cover fields the API doesn't expose.
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+#[graphql(complex)]
+pub struct AuditLog {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    #[graphql(skip)]
+    pub id: Option<ObjectId>,
+    #[graphql(skip)]
+    pub actor_id: Option<String>,
+    pub operation: AuditOperation,
+    pub collection: String,
+    #[graphql(skip)]
+    pub entity_id: ObjectId,
+    pub request_id: Option<String>,
+    #[graphql(skip)]
+    pub before: Option<Document>,
+    #[graphql(skip)]
+    pub after: Option<Document>,
+    pub changes: Vec<FieldChange>,
+    pub timestamp: DateTime,
+}
+
+#[ComplexObject]
+impl AuditLog {
+    async fn id(&self) -> Option<ID> {
+        self.id.map(|id| ID(id.to_hex()))
+    }
+
+    // Null for anonymous writes such as registration
+    async fn actor_id(&self) -> Option<ID> {
+        self.actor_id.clone().map(ID)
+    }
+
+    async fn entity_id(&self) -> ID {
+        ID(self.entity_id.to_hex())
+    }
+
+    async fn before(&self) -> Option<Json<serde_json::Value>> {
+        self.before.clone().map(|document| Json(Bson::Document(document).into_relaxed_extjson()))
+    }
+
+    async fn after(&self) -> Option<Json<serde_json::Value>> {
+        self.after.clone().map(|document| Json(Bson::Document(document).into_relaxed_extjson()))
+    }
+}
+
+impl ModelIndexes for AuditLog {
+    fn indexes() -> Vec<IndexSpec> {
+        vec![
+            IndexSpec::new(doc! { "entity_id": 1, "_id": 1 }).name("entity_id"),
+            IndexSpec::new(doc! { "actor_id": 1, "_id": 1 }).name("actor_id"),
+        ]
+    }
+}
+
+// Where a service records its writes, and on whose behalf. Services given a
+// trail write one entry per changed document from the CrudService methods.
+#[derive(Clone)]
+pub struct AuditTrail {
+    collection: Collection<AuditLog>,
+    actor_id: Option<String>,
+    request_id: Option<String>,
+}
+
+impl AuditTrail {
+    pub fn new(db: &Database) -> Self {
+        Self { collection: db.collection(AUDIT_COLLECTION), actor_id: None, request_id: None }
+    }
+
+    // Trail for the caller and request of a resolver
+    pub fn from_context(db: &Database, ctx: &Context<'_>) -> Self {
+        Self::new(db)
+            .with_actor(ctx.data_opt::<CurrentUser>().map(|user| user.id.clone()))
+            .with_request_id(ctx.data_opt::<RequestId>().map(|request_id| request_id.0.clone()))
+    }
+
+    pub fn with_actor(mut self, actor_id: Option<String>) -> Self {
+        self.actor_id = actor_id;
+        self
+    }
+
+    pub fn with_request_id(mut self, request_id: Option<String>) -> Self {
+        self.request_id = request_id;
+        self
+    }
+
+    // Record the documents of `collection` as they were before and after one
+    // write. Snapshots are paired by `_id`; updates that changed nothing are
+    // skipped.
+    pub async fn record(
+        &self,
+        collection: &str,
+        operation: AuditOperation,
+        before: Vec<Document>,
+        after: Vec<Document>,
+        session: Option<&mut ClientSession>,
+    ) -> ServiceResult<()> {
+        let timestamp = DateTime::now();
+        let entries = pair_snapshots(before, after)
+            .into_iter()
+            .filter_map(|(entity_id, before, after)| {
+                let changes = diff(before.as_ref(), after.as_ref());
+                if changes.is_empty() && operation == AuditOperation::Update {
+                    return None;
+                }
+                Some(AuditLog {
+                    id: None,
+                    actor_id: self.actor_id.clone(),
+                    operation,
+                    collection: collection.to_string(),
+                    entity_id,
+                    request_id: self.request_id.clone(),
+                    before: before.map(redact),
+                    after: after.map(redact),
+                    changes,
+                    timestamp,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if entries.is_empty() {
+            return Ok(());
+        }
+        match session {
+            Some(session) => {
+                self.collection.insert_many_with_session(entries, None, session).await?;
+            }
+            None => {
+                self.collection.insert_many(entries, None).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+type SnapshotPair = (ObjectId, Option<Document>, Option<Document>);
+
+// Match the snapshots of each document by `_id`, in the order first seen.
+// Documents without an ObjectId `_id` can't be tracked and are left out.
+fn pair_snapshots(before: Vec<Document>, after: Vec<Document>) -> Vec<SnapshotPair> {
+    let mut pairs: Vec<SnapshotPair> = Vec::new();
+    for document in before {
+        if let Ok(id) = document.get_object_id("_id") {
+            pairs.push((id, Some(document), None));
+        }
+    }
+    for document in after {
+        let Ok(id) = document.get_object_id("_id") else {
+            continue;
+        };
+        match pairs.iter_mut().find(|(entity_id, _, _)| *entity_id == id) {
+            Some((_, _, slot)) => *slot = Some(document),
+            None => pairs.push((id, None, Some(document))),
+        }
+    }
+    pairs
+}
+
+// Field-level diff of two snapshots, values of sensitive fields redacted
+fn diff(before: Option<&Document>, after: Option<&Document>) -> Vec<FieldChange> {
+    let empty = Document::new();
+    let (before, after) = (before.unwrap_or(&empty), after.unwrap_or(&empty));
+
+    before
+        .keys()
+        .chain(after.keys().filter(|field| !before.contains_key(field.as_str())))
+        .filter(|field| !AUDIT_IGNORED_FIELDS.contains(&field.as_str()))
+        .filter_map(|field| {
+            let (old, new) = (before.get(field), after.get(field));
+            (old != new).then(|| FieldChange {
+                field: field.clone(),
+                before: old.map(|value| redact_value(field, value)),
+                after: new.map(|value| redact_value(field, value)),
+            })
+        })
+        .collect()
+}
+
+fn redact(mut document: Document) -> Document {
+    for field in AUDIT_REDACTED_FIELDS {
+        if let Some(value) = document.get_mut(*field) {
+            *value = Bson::from(AUDIT_REDACTED);
+        }
+    }
+    document
+}
+
+fn redact_value(field: &str, value: &Bson) -> Bson {
+    if AUDIT_REDACTED_FIELDS.contains(&field) {
+        Bson::from(AUDIT_REDACTED)
+    } else {
+        value.clone()
+    }
+}
+
+// Id correlating the audit entries of one HTTP request. Taken from the
+// `x-request-id` header when the client or a proxy sets one, generated
+// otherwise.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+impl<S> FromRequestParts<S> for RequestId
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header = parts
+            .headers
+            .get("x-request-id")
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty() && value.len() <= REQUEST_ID_MAX_LENGTH);
+
+        Ok(Self(header.map(str::to_string).unwrap_or_else(|| ObjectId::new().to_hex())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(changes: &[FieldChange]) -> Vec<&str> {
+        changes.iter().map(|change| change.field.as_str()).collect()
+    }
+
+    #[test]
+    fn diff_lists_changed_added_and_removed_fields() {
+        let before = doc! { "_id": 1, "name": "Ann", "age": 30, "nickname": "A", "version": 1, "updated_at": 1 };
+        let after = doc! { "_id": 1, "name": "Ann", "age": 31, "email": "a@b.co", "version": 2, "updated_at": 2 };
+
+        let changes = diff(Some(&before), Some(&after));
+        assert_eq!(fields(&changes), vec!["age", "nickname", "email"]);
+        assert_eq!(changes[0].before, Some(Bson::Int32(30)));
+        assert_eq!(changes[0].after, Some(Bson::Int32(31)));
+        assert_eq!(changes[1].after, None);
+        assert_eq!(changes[2].before, None);
+    }
+
+    #[test]
+    fn diff_of_a_create_or_delete_covers_every_field() {
+        let document = doc! { "_id": 1, "name": "Ann", "version": 1 };
+        assert_eq!(fields(&diff(None, Some(&document))), vec!["name"]);
+        assert_eq!(fields(&diff(Some(&document), None)), vec!["name"]);
+        assert!(diff(Some(&document), Some(&document)).is_empty());
+    }
+
+    #[test]
+    fn sensitive_fields_are_redacted() {
+        let before = doc! { "_id": 1, "password_hash": "old" };
+        let after = doc! { "_id": 1, "password_hash": "new" };
+
+        let changes = diff(Some(&before), Some(&after));
+        assert_eq!(changes[0].before, Some(Bson::from(AUDIT_REDACTED)));
+        assert_eq!(changes[0].after, Some(Bson::from(AUDIT_REDACTED)));
+        assert_eq!(redact(after).get_str("password_hash"), Ok(AUDIT_REDACTED));
+    }
+
+    #[test]
+    fn snapshots_are_paired_by_id() {
+        let (a, b, c) = (ObjectId::new(), ObjectId::new(), ObjectId::new());
+        let before = vec![doc! { "_id": a, "n": 1 }, doc! { "_id": b, "n": 1 }, doc! { "_id": "text" }];
+        let after = vec![doc! { "_id": b, "n": 2 }, doc! { "_id": c, "n": 2 }];
+
+        let pairs = pair_snapshots(before, after)
+            .into_iter()
+            .map(|(id, before, after)| (id, before.is_some(), after.and_then(|d| d.get_i32("n").ok())))
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, vec![(a, true, None), (b, true, Some(2)), (c, false, Some(2))]);
+    }
+
+    #[test]
+    fn status_writes_map_to_lifecycle_operations() {
+        let deleted = doc! { "status": Status::Deleted.code() };
+        let active = doc! { "status": Status::Active.code() };
+        assert_eq!(AuditOperation::for_status(Status::Deleted, Some(&active)), AuditOperation::SoftDelete);
+        assert_eq!(AuditOperation::for_status(Status::Active, Some(&deleted)), AuditOperation::Restore);
+        assert_eq!(AuditOperation::for_status(Status::Inactive, Some(&active)), AuditOperation::Update);
+        assert_eq!(AuditOperation::for_status(Status::Active, None), AuditOperation::Update);
+    }
+}
@@ -0,0 +1,172 @@
+use std::any::TypeId;
+use std::sync::Arc;
+
+use axum::extract::FromRequestParts;
+use axum::http::{header::AUTHORIZATION, request::Parts, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use async_graphql::extensions::ExtensionContext;
+use async_graphql::{Enum, Request};
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+
+use crate::base::error::{ServiceError, ServiceResult};
+use crate::helpers::jwt::{JwtService, TokenType};
+
+#[derive(Enum, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Admin,
+    Moderator,
+    User,
+}
+
+#[derive(Enum, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    UsersRead,
+    UsersWrite,
+    UsersDelete,
+    RolesManage,
+}
+
+impl Role {
+    // Permissions granted by the role, on top of any granted to the user directly
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::Admin => &[
+                Permission::UsersRead,
+                Permission::UsersWrite,
+                Permission::UsersDelete,
+                Permission::RolesManage,
+            ],
+            Role::Moderator => &[Permission::UsersRead, Permission::UsersWrite],
+            Role::User => &[Permission::UsersRead],
+        }
+    }
+}
+
+pub fn default_roles() -> Vec<Role> {
+    vec![Role::User]
+}
+
+// The authenticated caller, placed in the GraphQL request data
+#[derive(Debug, Clone)]
+pub struct CurrentUser {
+    pub id: String,
+    pub roles: Vec<Role>,
+    pub permissions: Vec<Permission>,
+}
+
+impl CurrentUser {
+    // Decode the value of an `Authorization: Bearer <token>` header
+    pub fn from_bearer(jwt: &JwtService, header: &str) -> ServiceResult<Self> {
+        let token = header
+            .strip_prefix("Bearer ")
+            .or_else(|| header.strip_prefix("bearer "))
+            .ok_or_else(|| ServiceError::AuthenticationError("Expected a Bearer token".to_string()))?;
+
+        let claims = jwt.decode(token.trim(), TokenType::Access)?;
+        Ok(Self {
+            id: claims.sub,
+            roles: claims.roles,
+            permissions: claims.permissions,
+        })
+    }
+    
+    pub fn has_role(&self, role: Role) -> bool {
+        self.roles.contains(&role)
+    }
+    
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+            || self.roles.iter().any(|role| role.permissions().contains(&permission))
+    }
+}
+
+// Looks up the roles and permissions a user has now, None once the user is
+// deleted
+#[async_trait::async_trait]
+pub trait GrantSource: Send + Sync {
+    async fn grants(&self, user_id: &str) -> ServiceResult<Option<(Vec<Role>, Vec<Permission>)>>;
+}
+
+// Request data giving guards the caller's current grants. A token carries the
+// roles the user had when it was issued, so a demoted or deleted user would
+// keep them until it expires; guards check the stored ones instead. They are
+// looked up at most once per request.
+pub struct CurrentGrants {
+    source: Box<dyn GrantSource>,
+    user: OnceCell<Option<CurrentUser>>,
+}
+
+impl CurrentGrants {
+    pub fn new(source: impl GrantSource + 'static) -> Self {
+        Self { source: Box::new(source), user: OnceCell::new() }
+    }
+
+    // `user` with the grants stored now, None if the user no longer exists
+    pub async fn refresh(&self, user: &CurrentUser) -> ServiceResult<Option<CurrentUser>> {
+        let current = self
+            .user
+            .get_or_try_init(|| async {
+                let grants = self.source.grants(&user.id).await?;
+                Ok::<_, ServiceError>(grants.map(|(roles, permissions)| CurrentUser {
+                    id: user.id.clone(),
+                    roles,
+                    permissions,
+                }))
+            })
+            .await?;
+        Ok(current.clone())
+    }
+}
+
+// Caller of a request seen by a schema extension before it is prepared:
+// authenticated over HTTP (request data) or WebSocket (connection init data)
+pub fn request_user<'a>(ctx: &ExtensionContext<'a>, request: &'a Request) -> Option<&'a CurrentUser> {
+    request
+        .data
+        .get(&TypeId::of::<CurrentUser>())
+        .and_then(|data| data.downcast_ref::<CurrentUser>())
+        .or_else(|| ctx.data_opt::<CurrentUser>())
+}
+
+// Axum extractor for the optional bearer token. A missing header yields an
+// anonymous request; a malformed or expired token is rejected with 401 so
+// clients know to refresh.
+pub struct BearerAuth(pub Option<CurrentUser>);
+
+impl<S> FromRequestParts<S> for BearerAuth
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(header) = parts.headers.get(AUTHORIZATION) else {
+            return Ok(Self(None));
+        };
+
+        let jwt = parts
+            .extensions
+            .get::<Arc<JwtService>>()
+            .ok_or_else(|| reject(StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_SERVER_ERROR", "JWT service is not configured"))?;
+
+        let header = header
+            .to_str()
+            .map_err(|_| reject(StatusCode::UNAUTHORIZED, "AUTHENTICATION_ERROR", "Invalid Authorization header"))?;
+
+        CurrentUser::from_bearer(jwt, header)
+            .map(|user| Self(Some(user)))
+            .map_err(|e| reject(StatusCode::UNAUTHORIZED, "AUTHENTICATION_ERROR", &e.to_string()))
+    }
+}
+
+// Rejections use the GraphQL error shape so clients handle them like any other error
+fn reject(status: StatusCode, code: &str, message: &str) -> Response {
+    let body = serde_json::json!({
+        "errors": [{ "message": message, "extensions": { "code": code } }]
+    });
+    (status, Json(body)).into_response()
+}
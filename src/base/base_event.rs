@@ -1,7 +1,18 @@
-use tokio::sync::broadcast::{self, Sender, Receiver};
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::error::Error;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use futures::Stream;
+use tokio::sync::broadcast::{self, error::RecvError, Sender, Receiver};
+use tokio::task::JoinHandle;
+
+use crate::constants::events::EVENT_CHANNEL_CAPACITY;
 
 // Event type to represent different events in the system
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum EventType {
     Created,
     Updated,
@@ -20,11 +31,15 @@ where
 }
 
 // EventEmitter to broadcast events
+#[derive(Clone)]
 pub struct EventEmitter<T> 
 where 
     T: Clone + Send + Sync + 'static
 {
     sender: Sender<Event<T>>,
+    lagged: Arc<AtomicU64>,
+    external_source: Arc<AtomicBool>,
+    generation: Arc<AtomicU64>,
 }
 
 impl<T> EventEmitter<T> 
@@ -33,10 +48,16 @@ where
 {
     pub fn new(capacity: usize) -> Self {
         let (sender, _) = broadcast::channel(capacity);
-        Self { sender }
+        Self {
+            sender,
+            lagged: Arc::new(AtomicU64::new(0)),
+            external_source: Arc::new(AtomicBool::new(false)),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
     }
 
     pub fn emit(&self, event_type: EventType, payload: T) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
         let event = Event {
             event_type,
             payload,
@@ -46,22 +67,274 @@ where
         let _ = self.sender.send(event);
     }
     
+    // Emit an event for a write made by this process. Skipped when an external
+    // source (e.g. a MongoDB change stream) already publishes every write, so
+    // listeners don't receive the same change twice.
+    pub fn emit_local(&self, event_type: EventType, payload: T) {
+        if !self.has_external_source() {
+            self.emit(event_type, payload);
+        } else {
+            self.generation.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+    
+    pub fn set_external_source(&self, enabled: bool) {
+        self.external_source.store(enabled, Ordering::Relaxed);
+    }
+    
+    pub fn has_external_source(&self) -> bool {
+        self.external_source.load(Ordering::Relaxed)
+    }
+    
     pub fn subscribe(&self) -> Receiver<Event<T>> {
         self.sender.subscribe()
     }
+    
+    // Bumped synchronously by every emit, skipped local ones included, so
+    // caches can tell whether anything changed since they read a value
+    // without waiting for a handler to run
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+    
+    // Subscribe as a stream; events missed by a lagging receiver are skipped
+    // (and counted) and the stream ends when the emitter is dropped
+    pub fn stream(&self) -> impl Stream<Item = Event<T>> {
+        let lagged = self.lagged.clone();
+        futures::stream::unfold(self.subscribe(), move |mut receiver| {
+            let lagged = lagged.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) => return Some((event, receiver)),
+                        Err(RecvError::Lagged(skipped)) => record_lag::<T>(&lagged, skipped),
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        })
+    }
+    
+    pub fn receiver_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+    
+    // Total number of events dropped by receivers of this emitter that fell behind
+    pub fn lagged_count(&self) -> u64 {
+        self.lagged.load(Ordering::Relaxed)
+    }
+}
+
+fn record_lag<T>(lagged: &AtomicU64, skipped: u64) {
+    lagged.fetch_add(skipped, Ordering::Relaxed);
+    eprintln!("Event receiver for {} lagged behind, {} events skipped", type_name::<T>(), skipped);
 }
 
-// An event bus to manage multiple event types
+pub type HandlerId = u64;
+pub type HandlerError = Box<dyn Error + Send + Sync>;
+
+// Topic key: the payload type plus an optional name, so one payload type can
+// be published on several independent channels
+#[derive(Clone, Hash, PartialEq, Eq)]
+struct TopicKey {
+    type_id: TypeId,
+    name: Option<String>,
+}
+
+struct Topic {
+    name: String,
+    emitter: Box<dyn Any + Send + Sync>,
+    lagged: Arc<AtomicU64>,
+    receiver_count: Box<dyn Fn() -> usize + Send + Sync>,
+}
+
+// Per-topic statistics, used for lagged-receiver reporting
+#[derive(Debug, Clone)]
+pub struct TopicStats {
+    pub topic: String,
+    pub receivers: usize,
+    pub lagged: u64,
+}
+
+// An event bus holding one EventEmitter per payload type (and per named topic)
 pub struct EventBus {
-    // This would be implemented with a map of type ids to event emitters
-    // in a more complete implementation
+    capacity: usize,
+    topics: RwLock<HashMap<TopicKey, Topic>>,
+    handlers: Mutex<HashMap<HandlerId, JoinHandle<()>>>,
+    next_handler_id: AtomicU64,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EventBus {
     pub fn new() -> Self {
-        Self {}
+        Self::with_capacity(EVENT_CHANNEL_CAPACITY)
+    }
+    
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            topics: RwLock::new(HashMap::new()),
+            handlers: Mutex::new(HashMap::new()),
+            next_handler_id: AtomicU64::new(1),
+        }
+    }
+    
+    // Get the emitter for payload type T, creating it on first use
+    pub fn emitter<T>(&self) -> EventEmitter<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.get_or_create(TopicKey { type_id: TypeId::of::<T>(), name: None })
+    }
+    
+    // Get the emitter for a named topic carrying payload type T
+    pub fn topic<T>(&self, name: &str) -> EventEmitter<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.get_or_create(TopicKey { type_id: TypeId::of::<T>(), name: Some(name.to_string()) })
+    }
+    
+    fn get_or_create<T>(&self, key: TopicKey) -> EventEmitter<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        if let Some(topic) = self.topics.read().unwrap().get(&key) {
+            return downcast_emitter(topic);
+        }
+        
+        let mut topics = self.topics.write().unwrap();
+        let topic = topics.entry(key.clone()).or_insert_with(|| {
+            let emitter = EventEmitter::<T>::new(self.capacity);
+            let counter = emitter.clone();
+            Topic {
+                name: key.name.unwrap_or_else(|| type_name::<T>().to_string()),
+                lagged: emitter.lagged.clone(),
+                receiver_count: Box::new(move || counter.receiver_count()),
+                emitter: Box::new(emitter),
+            }
+        });
+        downcast_emitter(topic)
+    }
+    
+    pub fn publish<T>(&self, event_type: EventType, payload: T)
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.emitter::<T>().emit(event_type, payload);
+    }
+    
+    pub fn publish_to<T>(&self, topic: &str, event_type: EventType, payload: T)
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.topic::<T>(topic).emit(event_type, payload);
+    }
+    
+    pub fn subscribe<T>(&self) -> Receiver<Event<T>>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.emitter::<T>().subscribe()
+    }
+    
+    // Run `handler` for every event of type T until it is unregistered.
+    // Each invocation runs in its own task: an error or panic is logged and
+    // does not stop the handler from receiving later events.
+    pub fn register_handler<T, F, Fut>(&self, handler: F) -> HandlerId
+    where
+        T: Clone + Send + Sync + 'static,
+        F: Fn(Event<T>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+    {
+        self.spawn_handler(self.emitter::<T>(), handler)
     }
     
-    // For a complete implementation, you would have methods to register
-    // emitters for different event types and to get those emitters
+    pub fn register_topic_handler<T, F, Fut>(&self, topic: &str, handler: F) -> HandlerId
+    where
+        T: Clone + Send + Sync + 'static,
+        F: Fn(Event<T>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+    {
+        self.spawn_handler(self.topic::<T>(topic), handler)
+    }
+    
+    fn spawn_handler<T, F, Fut>(&self, emitter: EventEmitter<T>, handler: F) -> HandlerId
+    where
+        T: Clone + Send + Sync + 'static,
+        F: Fn(Event<T>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+    {
+        let id = self.next_handler_id.fetch_add(1, Ordering::Relaxed);
+        let mut receiver = emitter.subscribe();
+        let lagged = emitter.lagged.clone();
+        
+        let task = tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => match tokio::spawn(handler(event)).await {
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => eprintln!("Event handler {} for {} failed: {}", id, type_name::<T>(), e),
+                        Err(e) => eprintln!("Event handler {} for {} panicked: {}", id, type_name::<T>(), e),
+                    },
+                    Err(RecvError::Lagged(skipped)) => record_lag::<T>(&lagged, skipped),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+        
+        self.handlers.lock().unwrap().insert(id, task);
+        id
+    }
+    
+    // Stop a handler; returns false if the id is unknown
+    pub fn unregister_handler(&self, id: HandlerId) -> bool {
+        match self.handlers.lock().unwrap().remove(&id) {
+            Some(task) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+    
+    pub fn stats(&self) -> Vec<TopicStats> {
+        self.topics
+            .read()
+            .unwrap()
+            .values()
+            .map(|topic| TopicStats {
+                topic: topic.name.clone(),
+                receivers: (topic.receiver_count)(),
+                lagged: topic.lagged.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+impl Drop for EventBus {
+    fn drop(&mut self) {
+        if let Ok(handlers) = self.handlers.get_mut() {
+            for (_, task) in handlers.drain() {
+                task.abort();
+            }
+        }
+    }
+}
+
+fn downcast_emitter<T>(topic: &Topic) -> EventEmitter<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    // The key includes TypeId::of::<T>(), so the stored emitter always has this type
+    topic
+        .emitter
+        .downcast_ref::<EventEmitter<T>>()
+        .expect("event topic registered with a different payload type")
+        .clone()
 }
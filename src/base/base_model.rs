@@ -1,9 +1,18 @@
-use mongodb::bson::{oid::ObjectId, DateTime};
-use serde::{Deserialize, Serialize};
+use async_graphql::{ComplexObject, Enum, SimpleObject, ID};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::base::error::{ServiceError, ServiceResult};
+use crate::base::scalars::DateTime;
+
+// Fields shared by every model. Models embed it with
+// `#[serde(flatten)] #[graphql(flatten)]` so that `id`, `status`, `createdAt`
+// and `updatedAt` appear on their own GraphQL type.
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+#[graphql(complex)]
 pub struct BaseModel {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    #[graphql(skip)]
     pub id: Option<ObjectId>,
     
     #[serde(default = "default_created_at")]
@@ -12,8 +21,76 @@ pub struct BaseModel {
     #[serde(default = "default_updated_at")]
     pub updated_at: DateTime,
     
-    #[serde(default = "default_status")]
-    pub status: i32,
+    #[serde(default)]
+    pub status: Status,
+    
+    // Incremented by every write, used for optimistic concurrency
+    #[serde(default)]
+    pub version: i64,
+}
+
+pub const INITIAL_VERSION: i64 = 1;
+
+// Lifecycle of a record, stored as an integer in the `status` field
+#[derive(Enum, Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Status {
+    Inactive,
+    #[default]
+    Active,
+    // Soft deleted: hidden from reads until restored or purged
+    Deleted,
+}
+
+impl Status {
+    pub fn code(self) -> i32 {
+        match self {
+            Status::Inactive => 0,
+            Status::Active => 1,
+            Status::Deleted => -1,
+        }
+    }
+
+    pub fn from_code(code: i32) -> Option<Self> {
+        match code {
+            0 => Some(Status::Inactive),
+            1 => Some(Status::Active),
+            -1 => Some(Status::Deleted),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for Status {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i32(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for Status {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = i32::deserialize(deserializer)?;
+        Status::from_code(code)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid status: {}", code)))
+    }
+}
+
+// Condition matching records that have not been soft deleted; documents
+// written before `status` existed count as active
+pub fn not_deleted() -> Document {
+    doc! { "status": { "$ne": Status::Deleted.code() } }
+}
+
+// Restrict `filter` to records that have not been soft deleted
+pub fn exclude_deleted(filter: Document) -> Document {
+    if filter.is_empty() {
+        not_deleted()
+    } else if !filter.contains_key("status") {
+        let mut filter = filter;
+        filter.extend(not_deleted());
+        filter
+    } else {
+        doc! { "$and": [filter, not_deleted()] }
+    }
 }
 
 fn default_created_at() -> DateTime {
@@ -24,8 +101,17 @@ fn default_updated_at() -> DateTime {
     DateTime::now()
 }
 
-fn default_status() -> i32 {
-    1 // Assume 1 is active status
+impl Default for BaseModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[ComplexObject]
+impl BaseModel {
+    async fn id(&self) -> Option<ID> {
+        self.graphql_id()
+    }
 }
 
 impl BaseModel {
@@ -34,7 +120,46 @@ impl BaseModel {
             id: None,
             created_at: default_created_at(),
             updated_at: default_updated_at(),
-            status: default_status(),
+            status: Status::Active,
+            version: INITIAL_VERSION,
         }
     }
+    
+    pub fn graphql_id(&self) -> Option<ID> {
+        self.id.map(|id| ID(id.to_hex()))
+    }
+    
+    pub fn is_deleted(&self) -> bool {
+        self.status == Status::Deleted
+    }
+}
+
+// Conversion from the id representations used by models and resolvers
+// (ObjectId, hex strings, async_graphql::ID) to a MongoDB ObjectId
+pub trait ModelId: Send + Sync {
+    fn to_object_id(&self) -> ServiceResult<ObjectId>;
+}
+
+impl ModelId for ObjectId {
+    fn to_object_id(&self) -> ServiceResult<ObjectId> {
+        Ok(*self)
+    }
+}
+
+impl ModelId for str {
+    fn to_object_id(&self) -> ServiceResult<ObjectId> {
+        ObjectId::parse_str(self).map_err(|_| ServiceError::InvalidId(self.to_string()))
+    }
+}
+
+impl ModelId for String {
+    fn to_object_id(&self) -> ServiceResult<ObjectId> {
+        self.as_str().to_object_id()
+    }
+}
+
+impl ModelId for ID {
+    fn to_object_id(&self) -> ServiceResult<ObjectId> {
+        self.as_str().to_object_id()
+    }
 }
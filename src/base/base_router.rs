@@ -1,47 +1,40 @@
-use actix_web::{web, HttpResponse, Scope, Error as ActixError};
-use async_graphql::http::{GraphQLPlaygroundConfig, playground_source};
-use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
-
-use crate::graphql::schema::AppSchema;
-
-// Base router trait
-pub trait BaseRouter {
-    fn configure() -> Scope;
-}
-
-// GraphQL router implementation
-pub struct GraphQLRouter;
-
-impl GraphQLRouter {
-    pub async fn graphql_handler(schema: web::Data<AppSchema>, req: GraphQLRequest) -> GraphQLResponse {
-        schema.execute(req.into_inner()).await.into()
-    }
+// The HTTP stack of the app. Routers only build on this alias, so it is the
+// one place naming the server framework.
+pub type HttpRouter = axum::Router;
+
+// A group of HTTP routes, e.g. the GraphQL endpoint or the health checks.
+// Shared state (schema, JWT service) is layered on by the registry's caller,
+// so routers read it with `Extension` extractors.
+pub trait BaseRouter: Send + Sync {
+    fn routes(&self) -> HttpRouter;
     
-    pub async fn graphql_playground() -> Result<HttpResponse, ActixError> {
-        Ok(HttpResponse::Ok()
-            .content_type("text/html; charset=utf-8")
-            .body(playground_source(GraphQLPlaygroundConfig::new("/graphql"))))
-    }
-}
-
-impl BaseRouter for GraphQLRouter {
-    fn configure() -> Scope {
-        web::scope("/graphql")
-            .route("", web::post().to(Self::graphql_handler))
-            .route("", web::get().to(Self::graphql_playground))
+    // Routers that can be turned off by configuration override this
+    fn enabled(&self) -> bool {
+        true
     }
 }
 
-// Health check router
-pub struct HealthCheckRouter;
-
-async fn health_handler() -> HttpResponse {
-    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+// Collects every router so the app is mounted from one place, see
+// routers::app. Disabled routers are skipped.
+#[derive(Default)]
+pub struct RouterRegistry {
+    routers: Vec<Box<dyn BaseRouter>>,
 }
 
-impl BaseRouter for HealthCheckRouter {
-    fn configure() -> Scope {
-        web::scope("/health")
-            .route("", web::get().to(health_handler))
+impl RouterRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn register<R: BaseRouter + 'static>(mut self, router: R) -> Self {
+        self.routers.push(Box::new(router));
+        self
+    }
+    
+    pub fn into_router(self) -> HttpRouter {
+        self.routers
+            .iter()
+            .filter(|router| router.enabled())
+            .fold(HttpRouter::new(), |app, router| app.merge(router.routes()))
     }
 }
@@ -1,14 +1,22 @@
 use mongodb::{
-    bson::{doc, oid::ObjectId, Document},
-    Collection,
+    bson::{doc, from_document, oid::ObjectId, Document},
+    options::{FindOneOptions, FindOptions},
+    ClientSession, Collection,
 };
 use async_trait::async_trait;
 use futures::StreamExt;
-use std::error::Error;
+use std::collections::HashMap;
 use std::marker::{Send, Sync};
 
-use crate::base::error::ServiceError;
+use crate::base::audit::AuditTrail;
+use crate::base::base_model::{exclude_deleted, ModelId};
+use crate::base::error::ServiceResult;
+use crate::base::pagination::{paginate, Page, PageArgs};
+use crate::db::transaction::UnitOfWork;
 
+// Every read has a `*_with_session` variant for use inside a transaction.
+// The plain methods use the service's unit of work when it has one, so
+// callers normally don't need the session variants directly.
 #[async_trait]
 pub trait BaseService<T>
 where
@@ -16,51 +24,187 @@ where
 {
     fn get_collection(&self) -> Collection<T>;
     
-    async fn find_by_id(&self, id: &str) -> Result<Option<T>, Box<dyn Error>> {
-        let object_id = ObjectId::parse_str(id)
-            .map_err(|_| ServiceError::InvalidId(id.to_string()))?;
-            
-        let result = self.get_collection()
-            .find_one(doc! { "_id": object_id }, None)
-            .await
-            .map_err(|e| Box::new(ServiceError::DatabaseError(e.to_string())) as Box<dyn Error>)?;
-            
-        Ok(result)
+    // Soft deleted records are hidden from reads unless the service opts in
+    fn include_deleted(&self) -> bool {
+        false
     }
     
-    async fn find_many(&self, filter: Document, limit: Option<i64>, skip: Option<i64>) -> Result<Vec<T>, Box<dyn Error>> {
-        let mut options = mongodb::options::FindOptions::default();
-        
-        if let Some(limit_val) = limit {
-            options.limit = Some(limit_val);
+    // Per-request transaction the service takes part in, see db::transaction
+    fn unit_of_work(&self) -> Option<&UnitOfWork> {
+        None
+    }
+    
+    // Where CrudService writes are recorded, see base::audit
+    fn audit_trail(&self) -> Option<&AuditTrail> {
+        None
+    }
+    
+    fn scope(&self, filter: Document) -> Document {
+        if self.include_deleted() {
+            filter
+        } else {
+            exclude_deleted(filter)
         }
+    }
+    
+    async fn find_by_id<I>(&self, id: &I) -> ServiceResult<Option<T>>
+    where
+        I: ModelId + ?Sized,
+    {
+        let object_id = id.to_object_id()?;
+        self.find_one(doc! { "_id": object_id }).await
+    }
+    
+    async fn find_by_id_with_session<I>(&self, id: &I, session: &mut ClientSession) -> ServiceResult<Option<T>>
+    where
+        I: ModelId + ?Sized,
+    {
+        let object_id = id.to_object_id()?;
+        self.find_one_with_session(doc! { "_id": object_id }, session).await
+    }
+    
+    // Batch lookup by id, keyed by id; missing or out of scope ids are absent.
+    // Used by base::loader to coalesce id lookups of a request.
+    async fn find_by_ids(&self, ids: &[ObjectId]) -> ServiceResult<HashMap<ObjectId, T>> {
+        let mut cursor = self.get_collection()
+            .clone_with_type::<Document>()
+            .find(self.scope(doc! { "_id": { "$in": ids } }), None)
+            .await?;
         
-        if let Some(skip_val) = skip {
-            options.skip = Some(skip_val.try_into().unwrap_or(0));
+        let mut results = HashMap::with_capacity(ids.len());
+        while let Some(document) = cursor.next().await {
+            let document = document?;
+            if let Ok(id) = document.get_object_id("_id") {
+                results.insert(id, from_document(document)?);
+            }
         }
         
+        Ok(results)
+    }
+    
+    async fn find_one(&self, filter: Document) -> ServiceResult<Option<T>> {
+        if let Some(uow) = self.unit_of_work() {
+            return self.find_one_with_session(filter, &mut *uow.session().await?).await;
+        }
+    
+        let result = self.get_collection()
+            .find_one(self.scope(filter), None)
+            .await?;
+    
+        Ok(result)
+    }
+    
+    async fn find_one_with_session(&self, filter: Document, session: &mut ClientSession) -> ServiceResult<Option<T>> {
+        let result = self.get_collection()
+            .find_one_with_session(self.scope(filter), None, session)
+            .await?;
+    
+        Ok(result)
+    }
+    
+    async fn exists(&self, filter: Document) -> ServiceResult<bool> {
+        if let Some(uow) = self.unit_of_work() {
+            return self.exists_with_session(filter, &mut *uow.session().await?).await;
+        }
+    
+        let options = FindOneOptions::builder().projection(doc! { "_id": 1 }).build();
+        let result = self.get_collection()
+            .clone_with_type::<Document>()
+            .find_one(self.scope(filter), options)
+            .await?;
+    
+        Ok(result.is_some())
+    }
+    
+    async fn exists_with_session(&self, filter: Document, session: &mut ClientSession) -> ServiceResult<bool> {
+        let options = FindOneOptions::builder().projection(doc! { "_id": 1 }).build();
+        let result = self.get_collection()
+            .clone_with_type::<Document>()
+            .find_one_with_session(self.scope(filter), options, session)
+            .await?;
+    
+        Ok(result.is_some())
+    }
+    
+    async fn find_many(&self, filter: Document, limit: Option<i64>, skip: Option<i64>) -> ServiceResult<Vec<T>> {
+        self.find_many_sorted(filter, None, limit, skip).await
+    }
+    
+    async fn find_many_sorted(&self, filter: Document, sort: Option<Document>, limit: Option<i64>, skip: Option<i64>) -> ServiceResult<Vec<T>> {
+        if let Some(uow) = self.unit_of_work() {
+            return self.find_many_sorted_with_session(filter, sort, limit, skip, &mut *uow.session().await?).await;
+        }
+    
         let mut cursor = self.get_collection()
-            .find(filter, options)
-            .await
-            .map_err(|e| Box::new(ServiceError::DatabaseError(e.to_string())) as Box<dyn Error>)?;
-            
+            .find(self.scope(filter), find_options(sort, limit, skip))
+            .await?;
+    
         let mut results = Vec::new();
         while let Some(result) = cursor.next().await {
-            match result {
-                Ok(document) => results.push(document),
-                Err(e) => return Err(Box::new(ServiceError::DatabaseError(e.to_string()))),
-            }
+            results.push(result?);
+        }
+    
+        Ok(results)
+    }
+    
+    async fn find_many_sorted_with_session(
+        &self,
+        filter: Document,
+        sort: Option<Document>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+        session: &mut ClientSession,
+    ) -> ServiceResult<Vec<T>> {
+        let mut cursor = self.get_collection()
+            .find_with_session(self.scope(filter), find_options(sort, limit, skip), session)
+            .await?;
+    
+        let mut results = Vec::new();
+        while let Some(result) = cursor.next(session).await {
+            results.push(result?);
         }
-            
+    
         Ok(results)
     }
     
-    async fn count(&self, filter: Document) -> Result<u64, Box<dyn Error>> {
+    // Relay-style keyset page, see base::pagination. Pages are always read
+    // outside the unit of work.
+    async fn find_page(&self, filter: Document, args: PageArgs) -> ServiceResult<Page<T>> {
+        paginate(&self.get_collection(), self.scope(filter), args).await
+    }
+    
+    async fn count(&self, filter: Document) -> ServiceResult<u64> {
+        if let Some(uow) = self.unit_of_work() {
+            return self.count_with_session(filter, &mut *uow.session().await?).await;
+        }
+    
+        let count = self.get_collection()
+            .count_documents(self.scope(filter), None)
+            .await?;
+    
+        Ok(count)
+    }
+    
+    async fn count_with_session(&self, filter: Document, session: &mut ClientSession) -> ServiceResult<u64> {
         let count = self.get_collection()
-            .count_documents(filter, None)
-            .await
-            .map_err(|e| Box::new(ServiceError::DatabaseError(e.to_string())) as Box<dyn Error>)?;
-            
+            .count_documents_with_session(self.scope(filter), None, session)
+            .await?;
+    
         Ok(count)
     }
 }
+
+fn find_options(sort: Option<Document>, limit: Option<i64>, skip: Option<i64>) -> FindOptions {
+    let mut options = FindOptions::default();
+    options.sort = sort;
+
+    if let Some(limit_val) = limit {
+        options.limit = Some(limit_val);
+    }
+
+    if let Some(skip_val) = skip {
+        options.skip = Some(skip_val.try_into().unwrap_or(0));
+    }
+
+    options
+}
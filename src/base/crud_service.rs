@@ -1,65 +1,640 @@
 use mongodb::{
-    bson::{doc, oid::ObjectId, Document},
+    bson::{doc, oid::ObjectId, to_document, Bson, Document},
+    options::{FindOneAndUpdateOptions, FindOneOptions, FindOptions, ReturnDocument, UpdateOptions},
+    ClientSession,
 };
 use async_trait::async_trait;
+use futures::StreamExt;
 use serde::{de::DeserializeOwned, Serialize};
-use std::error::Error;
 use std::marker::{Send, Sync};
 
+use crate::base::audit::AuditOperation;
+use crate::constants::audit::AUDIT_BATCH_SIZE;
+use crate::base::base_model::{exclude_deleted, ModelId, Status, INITIAL_VERSION};
 use crate::base::base_service::BaseService;
-use crate::base::error::ServiceError;
+use crate::base::error::{ServiceError, ServiceResult};
+use crate::base::scalars::DateTime;
 
 #[async_trait]
 pub trait CrudService<T>: BaseService<T>
 where
     T: DeserializeOwned + Serialize + Unpin + Send + Sync + 'static,
 {
-    async fn create(&self, data: T) -> Result<T, Box<dyn Error>> {
+    // Insert `data`, stamping both timestamps and the initial version
+    // regardless of what the caller set
+    async fn create(&self, data: T) -> ServiceResult<T> {
+        if let Some(uow) = self.unit_of_work() {
+            return self.create_with_session(data, &mut *uow.session().await?).await;
+        }
+        
         let result = self.get_collection()
-            .insert_one(data, None)
-            .await
-            .map_err(|e| Box::new(ServiceError::DatabaseError(e.to_string())) as Box<dyn Error>)?;
-            
-        self.find_by_id(&result.inserted_id.as_object_id().unwrap().to_string()).await?
-            .ok_or_else(|| Box::new(ServiceError::NotFound("Created document not found".to_string())) as Box<dyn Error>)
+            .clone_with_type::<Document>()
+            .insert_one(stamped(&data)?, None)
+            .await?;
+        
+        let object_id = inserted_id(&result.inserted_id)?;
+        self.audit(AuditOperation::Create, Vec::new(), Some(doc! { "_id": object_id }), None).await?;
+        
+        self.find_by_id(&object_id).await?
+            .ok_or_else(|| ServiceError::NotFound("Created document not found".to_string()))
     }
     
-    async fn update(&self, id: &str, data: Document) -> Result<Option<T>, Box<dyn Error>> {
-        let object_id = ObjectId::parse_str(id)
-            .map_err(|_| ServiceError::InvalidId(id.to_string()))?;
-            
-        // Add updated_at field to the update document
-        let mut update_doc = data.clone();
-        update_doc.insert("updated_at", mongodb::bson::DateTime::now());
+    async fn create_with_session(&self, data: T, session: &mut ClientSession) -> ServiceResult<T> {
+        let result = self.get_collection()
+            .clone_with_type::<Document>()
+            .insert_one_with_session(stamped(&data)?, None, session)
+            .await?;
         
-        let update = doc! { "$set": update_doc };
+        let object_id = inserted_id(&result.inserted_id)?;
+        self.audit(AuditOperation::Create, Vec::new(), Some(doc! { "_id": object_id }), Some(session)).await?;
         
-        let _ = self.get_collection()
-            .update_one(doc! { "_id": object_id }, update, None)
-            .await
-            .map_err(|e| Box::new(ServiceError::DatabaseError(e.to_string())) as Box<dyn Error>)?;
-            
-        self.find_by_id(id).await
+        self.find_by_id_with_session(&object_id, session).await?
+            .ok_or_else(|| ServiceError::NotFound("Created document not found".to_string()))
     }
     
-    async fn delete(&self, id: &str) -> Result<bool, Box<dyn Error>> {
-        let object_id = ObjectId::parse_str(id)
-            .map_err(|_| ServiceError::InvalidId(id.to_string()))?;
-            
+    // Apply `$set` to one document by id and return it after the update.
+    // With `expected_version` the write only happens if the stored version
+    // still matches, otherwise it fails with a version conflict.
+    async fn update<I>(&self, id: &I, data: Document, expected_version: Option<i64>) -> ServiceResult<Option<T>>
+    where
+        I: ModelId + ?Sized,
+    {
+        if let Some(uow) = self.unit_of_work() {
+            return self.update_with_session(id, data, expected_version, &mut *uow.session().await?).await;
+        }
+        
+        let object_id = id.to_object_id()?;
+        let filter = with_version(doc! { "_id": object_id }, expected_version);
+        match self.find_one_and_update(filter, doc! { "$set": data }).await? {
+            Some(updated) => Ok(Some(updated)),
+            None => self.missed_write(object_id, expected_version, None).await,
+        }
+    }
+    
+    async fn update_with_session<I>(
+        &self,
+        id: &I,
+        data: Document,
+        expected_version: Option<i64>,
+        session: &mut ClientSession,
+    ) -> ServiceResult<Option<T>>
+    where
+        I: ModelId + ?Sized,
+    {
+        let object_id = id.to_object_id()?;
+        let filter = with_version(doc! { "_id": object_id }, expected_version);
+        match self.find_one_and_update_with_session(filter, doc! { "$set": data }, session).await? {
+            Some(updated) => Ok(Some(updated)),
+            None => self.missed_write(object_id, expected_version, Some(session)).await,
+        }
+    }
+    
+    // Apply a raw update document to the first match and return it after the update
+    async fn find_one_and_update(&self, filter: Document, update: Document) -> ServiceResult<Option<T>> {
+        if let Some(uow) = self.unit_of_work() {
+            return self.find_one_and_update_with_session(filter, update, &mut *uow.session().await?).await;
+        }
+        
+        let filter = self.scope(filter);
+        let before = self.audit_snapshot(filter.clone(), Some(1), None).await?;
+        
+        let result = self.get_collection()
+            .find_one_and_update(filter, touch(update), return_after())
+            .await?;
+        
+        if result.is_some() {
+            self.audit(AuditOperation::Update, before.clone(), Some(ids_filter(&before)), None).await?;
+        }
+        Ok(result)
+    }
+    
+    async fn find_one_and_update_with_session(
+        &self,
+        filter: Document,
+        update: Document,
+        session: &mut ClientSession,
+    ) -> ServiceResult<Option<T>> {
+        let filter = self.scope(filter);
+        let before = self.audit_snapshot(filter.clone(), Some(1), Some(session)).await?;
+        
+        let result = self.get_collection()
+            .find_one_and_update_with_session(filter, touch(update), return_after(), session)
+            .await?;
+        
+        if result.is_some() {
+            self.audit(AuditOperation::Update, before.clone(), Some(ids_filter(&before)), Some(session)).await?;
+        }
+        Ok(result)
+    }
+    
+    // Apply `$set` to the first match, inserting a new document built from the
+    // filter and `data` when nothing matches
+    async fn upsert(&self, filter: Document, data: Document) -> ServiceResult<T> {
+        if let Some(uow) = self.unit_of_work() {
+            return self.upsert_with_session(filter, data, &mut *uow.session().await?).await;
+        }
+        
+        let before = self.audit_snapshot(filter.clone(), Some(1), None).await?;
+        
+        let result = self.get_collection()
+            .find_one_and_update(filter.clone(), upsert_update(data), upsert_options())
+            .await?
+            .ok_or_else(|| ServiceError::InternalServerError("Upserted document not returned".to_string()))?;
+        
+        let (operation, after) = upsert_audit(&before, filter);
+        self.audit(operation, before, Some(after), None).await?;
+        Ok(result)
+    }
+    
+    async fn upsert_with_session(&self, filter: Document, data: Document, session: &mut ClientSession) -> ServiceResult<T> {
+        let before = self.audit_snapshot(filter.clone(), Some(1), Some(session)).await?;
+        
+        let result = self.get_collection()
+            .find_one_and_update_with_session(filter.clone(), upsert_update(data), upsert_options(), session)
+            .await?
+            .ok_or_else(|| ServiceError::InternalServerError("Upserted document not returned".to_string()))?;
+        
+        let (operation, after) = upsert_audit(&before, filter);
+        self.audit(operation, before, Some(after), Some(session)).await?;
+        Ok(result)
+    }
+    
+    async fn update_many(&self, filter: Document, data: Document) -> ServiceResult<u64> {
+        if let Some(uow) = self.unit_of_work() {
+            return self.update_many_with_session(filter, data, &mut *uow.session().await?).await;
+        }
+        
+        let update = touch(doc! { "$set": data });
+        self.write_in_batches(self.scope(filter), Some(update), AuditOperation::Update, None).await
+    }
+    
+    async fn update_many_with_session(&self, filter: Document, data: Document, session: &mut ClientSession) -> ServiceResult<u64> {
+        let update = touch(doc! { "$set": data });
+        self.write_in_batches(self.scope(filter), Some(update), AuditOperation::Update, Some(session)).await
+    }
+    
+    // Mark one record as deleted and return it; None if missing or already deleted
+    async fn soft_delete<I>(&self, id: &I, expected_version: Option<i64>) -> ServiceResult<Option<T>>
+    where
+        I: ModelId + ?Sized,
+    {
+        if let Some(uow) = self.unit_of_work() {
+            return self.soft_delete_with_session(id, expected_version, &mut *uow.session().await?).await;
+        }
+        
+        let object_id = id.to_object_id()?;
+        let filter = with_version(exclude_deleted(doc! { "_id": object_id }), expected_version);
+        match self.set_status(filter, Status::Deleted).await? {
+            Some(deleted) => Ok(Some(deleted)),
+            None => self.missed_write(object_id, expected_version, None).await,
+        }
+    }
+    
+    async fn soft_delete_with_session<I>(
+        &self,
+        id: &I,
+        expected_version: Option<i64>,
+        session: &mut ClientSession,
+    ) -> ServiceResult<Option<T>>
+    where
+        I: ModelId + ?Sized,
+    {
+        let object_id = id.to_object_id()?;
+        let filter = with_version(exclude_deleted(doc! { "_id": object_id }), expected_version);
+        match self.set_status_with_session(filter, Status::Deleted, session).await? {
+            Some(deleted) => Ok(Some(deleted)),
+            None => self.missed_write(object_id, expected_version, Some(session)).await,
+        }
+    }
+    
+    // Tell a stale `expected_version` apart from a missing record after a
+    // conditional write matched nothing
+    async fn missed_write(
+        &self,
+        object_id: ObjectId,
+        expected_version: Option<i64>,
+        session: Option<&mut ClientSession>,
+    ) -> ServiceResult<Option<T>> {
+        let Some(expected_version) = expected_version else {
+            return Ok(None);
+        };
+        
+        let collection = self.get_collection().clone_with_type::<Document>();
+        let filter = self.scope(doc! { "_id": object_id });
+        let options = FindOneOptions::builder().projection(doc! { "version": 1 }).build();
+        let current = match session {
+            Some(session) => collection.find_one_with_session(filter, options, session).await?,
+            None => collection.find_one(filter, options).await?,
+        };
+        
+        match current.map(|document| stored_version(&document)) {
+            Some(current_version) if current_version != expected_version => {
+                Err(ServiceError::VersionConflict(current_version))
+            }
+            _ => Ok(None),
+        }
+    }
+    
+    async fn soft_delete_many(&self, filter: Document) -> ServiceResult<u64> {
+        if let Some(uow) = self.unit_of_work() {
+            return self.soft_delete_many_with_session(filter, &mut *uow.session().await?).await;
+        }
+        
+        self.write_in_batches(exclude_deleted(filter), Some(mark_deleted()), AuditOperation::SoftDelete, None).await
+    }
+    
+    async fn soft_delete_many_with_session(&self, filter: Document, session: &mut ClientSession) -> ServiceResult<u64> {
+        let filter = exclude_deleted(filter);
+        self.write_in_batches(filter, Some(mark_deleted()), AuditOperation::SoftDelete, Some(session)).await
+    }
+    
+    // Bring a soft deleted record back as active; None if it is not deleted
+    async fn restore<I>(&self, id: &I) -> ServiceResult<Option<T>>
+    where
+        I: ModelId + ?Sized,
+    {
+        let object_id = id.to_object_id()?;
+        let filter = doc! { "_id": object_id, "status": Status::Deleted.code() };
+        self.set_status(filter, Status::Active).await
+    }
+    
+    // Permanently remove a record, whether or not it was soft deleted
+    async fn purge<I>(&self, id: &I) -> ServiceResult<Option<T>>
+    where
+        I: ModelId + ?Sized,
+    {
+        self.find_by_id_and_delete(id).await
+    }
+    
+    // Status transitions bypass `scope` so they can reach deleted records
+    async fn set_status(&self, filter: Document, status: Status) -> ServiceResult<Option<T>> {
+        if let Some(uow) = self.unit_of_work() {
+            return self.set_status_with_session(filter, status, &mut *uow.session().await?).await;
+        }
+        
+        let before = self.audit_snapshot(filter.clone(), Some(1), None).await?;
+        
+        let result = self.get_collection()
+            .find_one_and_update(
+                filter,
+                touch(doc! { "$set": { "status": status.code() } }),
+                return_after(),
+            )
+            .await?;
+        
+        if result.is_some() {
+            let operation = AuditOperation::for_status(status, before.first());
+            self.audit(operation, before.clone(), Some(ids_filter(&before)), None).await?;
+        }
+        Ok(result)
+    }
+    
+    async fn set_status_with_session(&self, filter: Document, status: Status, session: &mut ClientSession) -> ServiceResult<Option<T>> {
+        let before = self.audit_snapshot(filter.clone(), Some(1), Some(session)).await?;
+        
+        let result = self.get_collection()
+            .find_one_and_update_with_session(
+                filter,
+                touch(doc! { "$set": { "status": status.code() } }),
+                return_after(),
+                session,
+            )
+            .await?;
+        
+        if result.is_some() {
+            let operation = AuditOperation::for_status(status, before.first());
+            self.audit(operation, before.clone(), Some(ids_filter(&before)), Some(session)).await?;
+        }
+        Ok(result)
+    }
+    
+    // Hard delete, see `soft_delete` for the reversible variant
+    async fn delete<I>(&self, id: &I) -> ServiceResult<bool>
+    where
+        I: ModelId + ?Sized,
+    {
+        if let Some(uow) = self.unit_of_work() {
+            return self.delete_with_session(id, &mut *uow.session().await?).await;
+        }
+        
+        let object_id = id.to_object_id()?;
+        let before = self.audit_snapshot(doc! { "_id": object_id }, Some(1), None).await?;
+        
         let result = self.get_collection()
             .delete_one(doc! { "_id": object_id }, None)
-            .await
-            .map_err(|e| Box::new(ServiceError::DatabaseError(e.to_string())) as Box<dyn Error>)?;
-            
+            .await?;
+        
+        if result.deleted_count > 0 {
+            self.audit(AuditOperation::Delete, before, None, None).await?;
+        }
         Ok(result.deleted_count > 0)
     }
     
-    async fn delete_many(&self, filter: Document) -> Result<u64, Box<dyn Error>> {
+    async fn delete_with_session<I>(&self, id: &I, session: &mut ClientSession) -> ServiceResult<bool>
+    where
+        I: ModelId + ?Sized,
+    {
+        let object_id = id.to_object_id()?;
+        let before = self.audit_snapshot(doc! { "_id": object_id }, Some(1), Some(session)).await?;
+        
+        let result = self.get_collection()
+            .delete_one_with_session(doc! { "_id": object_id }, None, session)
+            .await?;
+        
+        if result.deleted_count > 0 {
+            self.audit(AuditOperation::Delete, before, None, Some(session)).await?;
+        }
+        Ok(result.deleted_count > 0)
+    }
+    
+    // Delete one document by id and return it as it was before deletion
+    async fn find_by_id_and_delete<I>(&self, id: &I) -> ServiceResult<Option<T>>
+    where
+        I: ModelId + ?Sized,
+    {
+        if let Some(uow) = self.unit_of_work() {
+            return self.find_by_id_and_delete_with_session(id, &mut *uow.session().await?).await;
+        }
+        
+        let object_id = id.to_object_id()?;
+        let before = self.audit_snapshot(doc! { "_id": object_id }, Some(1), None).await?;
+        
+        let result = self.get_collection()
+            .find_one_and_delete(doc! { "_id": object_id }, None)
+            .await?;
+        
+        if result.is_some() {
+            self.audit(AuditOperation::Delete, before, None, None).await?;
+        }
+        Ok(result)
+    }
+    
+    async fn find_by_id_and_delete_with_session<I>(&self, id: &I, session: &mut ClientSession) -> ServiceResult<Option<T>>
+    where
+        I: ModelId + ?Sized,
+    {
+        let object_id = id.to_object_id()?;
+        let before = self.audit_snapshot(doc! { "_id": object_id }, Some(1), Some(session)).await?;
+        
         let result = self.get_collection()
-            .delete_many(filter, None)
-            .await
-            .map_err(|e| Box::new(ServiceError::DatabaseError(e.to_string())) as Box<dyn Error>)?;
+            .find_one_and_delete_with_session(doc! { "_id": object_id }, None, session)
+            .await?;
+        
+        if result.is_some() {
+            self.audit(AuditOperation::Delete, before, None, Some(session)).await?;
+        }
+        Ok(result)
+    }
+    
+    async fn delete_many(&self, filter: Document) -> ServiceResult<u64> {
+        if let Some(uow) = self.unit_of_work() {
+            return self.delete_many_with_session(filter, &mut *uow.session().await?).await;
+        }
+        
+        self.write_in_batches(filter, None, AuditOperation::Delete, None).await
+    }
+    
+    async fn delete_many_with_session(&self, filter: Document, session: &mut ClientSession) -> ServiceResult<u64> {
+        self.write_in_batches(filter, None, AuditOperation::Delete, Some(session)).await
+    }
+    
+    // Apply `update` to every document matching `filter`, or delete them when
+    // it is None, and return how many were changed. With an audit trail the
+    // documents are written in `_id` order, AUDIT_BATCH_SIZE at a time, so the
+    // before and after snapshots never hold more than one batch in memory.
+    async fn write_in_batches(
+        &self,
+        filter: Document,
+        update: Option<Document>,
+        operation: AuditOperation,
+        mut session: Option<&mut ClientSession>,
+    ) -> ServiceResult<u64> {
+        if self.audit_trail().is_none() {
+            return self.write_many(filter, update.as_ref(), session).await;
+        }
+        
+        let mut written = 0;
+        let mut last_id: Option<Bson> = None;
+        loop {
+            let page_filter = match &last_id {
+                Some(last_id) => doc! { "$and": [filter.clone(), { "_id": { "$gt": last_id } }] },
+                None => filter.clone(),
+            };
+            let before = self.audit_snapshot(page_filter, Some(AUDIT_BATCH_SIZE), session.as_deref_mut()).await?;
+            let Some(batch_last_id) = before.last().and_then(|document| document.get("_id")).cloned() else {
+                break;
+            };
+            let full_batch = before.len() as i64 == AUDIT_BATCH_SIZE;
+            
+            // The original filter still applies in case a document changed since the snapshot
+            let ids = ids_filter(&before);
+            let batch_filter = doc! { "$and": [filter.clone(), ids.clone()] };
+            written += self.write_many(batch_filter, update.as_ref(), session.as_deref_mut()).await?;
+            
+            let after = update.is_some().then_some(ids);
+            self.audit(operation, before, after, session.as_deref_mut()).await?;
             
-        Ok(result.deleted_count)
+            if !full_batch {
+                break;
+            }
+            last_id = Some(batch_last_id);
+        }
+        Ok(written)
+    }
+    
+    // One update_many or delete_many, without auditing
+    async fn write_many(
+        &self,
+        filter: Document,
+        update: Option<&Document>,
+        session: Option<&mut ClientSession>,
+    ) -> ServiceResult<u64> {
+        let collection = self.get_collection();
+        Ok(match (update, session) {
+            (Some(update), Some(session)) => {
+                collection
+                    .update_many_with_session(filter, update.clone(), UpdateOptions::default(), session)
+                    .await?
+                    .modified_count
+            }
+            (Some(update), None) => {
+                collection.update_many(filter, update.clone(), UpdateOptions::default()).await?.modified_count
+            }
+            (None, Some(session)) => collection.delete_many_with_session(filter, None, session).await?.deleted_count,
+            (None, None) => collection.delete_many(filter, None).await?.deleted_count,
+        })
+    }
+    
+    // Raw copies of the documents matching `filter`, read only when the
+    // service keeps an audit trail. Inside a unit of work they are exact,
+    // otherwise a concurrent write may land between snapshot and write.
+    async fn audit_snapshot(
+        &self,
+        filter: Document,
+        limit: Option<i64>,
+        session: Option<&mut ClientSession>,
+    ) -> ServiceResult<Vec<Document>> {
+        if self.audit_trail().is_none() {
+            return Ok(Vec::new());
+        }
+        
+        let collection = self.get_collection().clone_with_type::<Document>();
+        let mut options = FindOptions::default();
+        options.limit = limit;
+        options.sort = Some(doc! { "_id": 1 });
+        
+        let mut documents = Vec::new();
+        match session {
+            Some(session) => {
+                let mut cursor = collection.find_with_session(filter, options, session).await?;
+                while let Some(document) = cursor.next(session).await {
+                    documents.push(document?);
+                }
+            }
+            None => {
+                let mut cursor = collection.find(filter, options).await?;
+                while let Some(document) = cursor.next().await {
+                    documents.push(document?);
+                }
+            }
+        }
+        Ok(documents)
+    }
+    
+    // Record a write in the audit trail; `after` selects the documents to
+    // snapshot once the write is done, None for deletions. Inside a session a
+    // failure rolls the write back with it. Without one the write is already
+    // persisted, so the failure is only logged and the caller still goes on
+    // to publish the change.
+    async fn audit(
+        &self,
+        operation: AuditOperation,
+        before: Vec<Document>,
+        after: Option<Document>,
+        mut session: Option<&mut ClientSession>,
+    ) -> ServiceResult<()> {
+        let Some(trail) = self.audit_trail() else {
+            return Ok(());
+        };
+        
+        let in_session = session.is_some();
+        let recorded = async {
+            let after = match after {
+                Some(filter) => self.audit_snapshot(filter, None, session.as_deref_mut()).await?,
+                None => Vec::new(),
+            };
+            trail.record(self.get_collection().name(), operation, before, after, session).await
+        }
+        .await;
+        
+        match recorded {
+            Err(e) if !in_session => {
+                eprintln!(
+                    "Could not record {:?} on {} in the audit log: {}",
+                    operation,
+                    self.get_collection().name(),
+                    e
+                );
+                Ok(())
+            }
+            result => result,
+        }
+    }
+}
+
+// Stamp `updated_at` and bump `version` on an update document, keeping its
+// other operators
+pub fn touch(mut update: Document) -> Document {
+    let now = DateTime::now();
+    match update.get_document_mut("$set") {
+        Ok(set) => {
+            set.insert("updated_at", now);
+        }
+        Err(_) => {
+            update.insert("$set", doc! { "updated_at": now });
+        }
+    }
+    match update.get_document_mut("$inc") {
+        Ok(inc) => {
+            inc.insert("version", 1_i64);
+        }
+        Err(_) => {
+            update.insert("$inc", doc! { "version": 1_i64 });
+        }
+    }
+    update
+}
+
+// Restrict `filter` to the expected version. Documents written before
+// versioning have no `version` field and count as version 0.
+fn with_version(mut filter: Document, expected_version: Option<i64>) -> Document {
+    if let Some(expected_version) = expected_version {
+        let condition = match expected_version {
+            0 => Bson::Document(doc! { "$in": [0_i64, Bson::Null] }),
+            version => Bson::Int64(version),
+        };
+        filter.insert("version", condition);
+    }
+    filter
+}
+
+fn stored_version(document: &Document) -> i64 {
+    match document.get("version") {
+        Some(Bson::Int64(version)) => *version,
+        Some(Bson::Int32(version)) => i64::from(*version),
+        _ => 0,
+    }
+}
+
+// Serialize a new record with both timestamps and the initial version
+fn stamped<T: Serialize>(data: &T) -> ServiceResult<Document> {
+    let mut document = to_document(data)?;
+    let now = DateTime::now();
+    document.insert("created_at", now);
+    document.insert("updated_at", now);
+    document.insert("version", INITIAL_VERSION);
+    Ok(document)
+}
+
+fn inserted_id(id: &Bson) -> ServiceResult<ObjectId> {
+    id.as_object_id()
+        .ok_or_else(|| ServiceError::InternalServerError("Inserted id is not an ObjectId".to_string()))
+}
+
+fn upsert_update(data: Document) -> Document {
+    let mut update = touch(doc! { "$set": data });
+    update.insert("$setOnInsert", doc! { "created_at": DateTime::now() });
+    update
+}
+
+fn upsert_options() -> FindOneAndUpdateOptions {
+    FindOneAndUpdateOptions::builder()
+        .upsert(true)
+        .return_document(ReturnDocument::After)
+        .build()
+}
+
+fn return_after() -> FindOneAndUpdateOptions {
+    FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build()
+}
+
+fn mark_deleted() -> Document {
+    touch(doc! { "$set": { "status": Status::Deleted.code() } })
+}
+
+// Select the snapshotted documents again by id
+fn ids_filter(documents: &[Document]) -> Document {
+    let ids = documents.iter().filter_map(|document| document.get("_id").cloned()).collect::<Vec<_>>();
+    doc! { "_id": { "$in": ids } }
+}
+
+// An upsert that matched nothing created the document
+fn upsert_audit(before: &[Document], filter: Document) -> (AuditOperation, Document) {
+    if before.is_empty() {
+        (AuditOperation::Create, filter)
+    } else {
+        (AuditOperation::Update, ids_filter(before))
     }
 }
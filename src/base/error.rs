@@ -1,15 +1,33 @@
 use std::error::Error;
 use std::fmt;
 use async_graphql::ErrorExtensions;
+use mongodb::error::{ErrorKind, WriteFailure, TRANSIENT_TRANSACTION_ERROR};
 
-#[derive(Debug)]
+use crate::base::validation::FieldError;
+
+// Duplicate key error code returned by MongoDB
+const DUPLICATE_KEY_CODE: i32 = 11000;
+
+pub type ServiceResult<T> = Result<T, ServiceError>;
+
+#[derive(Debug, Clone)]
 pub enum ServiceError {
     NotFound(String),
     InvalidId(String),
     ValidationError(String),
+    // Per-field errors from base::validation
+    InvalidInput(Vec<FieldError>),
     DatabaseError(String),
     AuthenticationError(String),
     AuthorizationError(String),
+    Conflict(String),
+    // Optimistic concurrency check failed; holds the current version
+    VersionConflict(i64),
+    // Unique constraint violated; holds the offending field
+    AlreadyExists(String),
+    // Transaction aborted by the server and safe to run again
+    TransientTransaction(String),
+    ServiceUnavailable(String),
     InternalServerError(String),
 }
 
@@ -19,9 +37,18 @@ impl fmt::Display for ServiceError {
             ServiceError::NotFound(msg) => write!(f, "Not found: {}", msg),
             ServiceError::InvalidId(id) => write!(f, "Invalid ID: {}", id),
             ServiceError::ValidationError(msg) => write!(f, "Validation error: {}", msg),
+            ServiceError::InvalidInput(errors) => {
+                let fields = errors.iter().map(|e| format!("{} {}", e.field, e.message)).collect::<Vec<_>>();
+                write!(f, "Invalid input: {}", fields.join(", "))
+            }
             ServiceError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
             ServiceError::AuthenticationError(msg) => write!(f, "Authentication error: {}", msg),
             ServiceError::AuthorizationError(msg) => write!(f, "Authorization error: {}", msg),
+            ServiceError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            ServiceError::VersionConflict(version) => write!(f, "Version conflict: current version is {}", version),
+            ServiceError::AlreadyExists(field) => write!(f, "Already exists: {}", field),
+            ServiceError::TransientTransaction(msg) => write!(f, "Transient transaction error: {}", msg),
+            ServiceError::ServiceUnavailable(msg) => write!(f, "Service unavailable: {}", msg),
             ServiceError::InternalServerError(msg) => write!(f, "Internal server error: {}", msg),
         }
     }
@@ -30,7 +57,6 @@ impl fmt::Display for ServiceError {
 impl Error for ServiceError {}
 
 // Convert service errors to GraphQL errors
-// Instead of implementing From, we provide a method to convert to GraphQL error
 impl ServiceError {
     pub fn to_graphql_error(&self) -> async_graphql::Error {
         match self {
@@ -49,7 +75,14 @@ impl ServiceError {
                     e.set("code", "VALIDATION_ERROR")
                 })
             }
-            ServiceError::DatabaseError(msg) => {
+            ServiceError::InvalidInput(errors) => {
+                let field_errors = async_graphql::to_value(errors).unwrap_or_default();
+                async_graphql::Error::new("Invalid input").extend_with(|_, e| {
+                    e.set("code", "VALIDATION_ERROR");
+                    e.set("fieldErrors", field_errors);
+                })
+            }
+            ServiceError::DatabaseError(_) => {
                 async_graphql::Error::new("Database error occurred").extend_with(|_, e| {
                     e.set("code", "DATABASE_ERROR")
                 })
@@ -64,7 +97,36 @@ impl ServiceError {
                     e.set("code", "AUTHORIZATION_ERROR")
                 })
             }
-            ServiceError::InternalServerError(msg) => {
+            ServiceError::Conflict(msg) => {
+                async_graphql::Error::new(msg).extend_with(|_, e| {
+                    e.set("code", "CONFLICT")
+                })
+            }
+            ServiceError::VersionConflict(current_version) => {
+                let current_version = *current_version;
+                async_graphql::Error::new("The record was modified by another request").extend_with(|_, e| {
+                    e.set("code", "CONFLICT");
+                    e.set("currentVersion", current_version);
+                })
+            }
+            ServiceError::AlreadyExists(field) => {
+                let field = field.clone();
+                async_graphql::Error::new(format!("A record with this {} already exists", field)).extend_with(|_, e| {
+                    e.set("code", "ALREADY_EXISTS");
+                    e.set("field", field);
+                })
+            }
+            ServiceError::TransientTransaction(_) => {
+                async_graphql::Error::new("The request conflicted with a concurrent write, please retry").extend_with(|_, e| {
+                    e.set("code", "CONFLICT")
+                })
+            }
+            ServiceError::ServiceUnavailable(_) => {
+                async_graphql::Error::new("Service temporarily unavailable").extend_with(|_, e| {
+                    e.set("code", "SERVICE_UNAVAILABLE")
+                })
+            }
+            ServiceError::InternalServerError(_) => {
                 async_graphql::Error::new("Internal server error").extend_with(|_, e| {
                     e.set("code", "INTERNAL_SERVER_ERROR")
                 })
@@ -72,3 +134,118 @@ impl ServiceError {
         }
     }
 }
+
+impl From<ServiceError> for async_graphql::Error {
+    fn from(err: ServiceError) -> Self {
+        err.to_graphql_error()
+    }
+}
+
+// Map driver errors by kind so callers can tell a conflict or an outage
+// apart from a generic database failure
+impl From<mongodb::error::Error> for ServiceError {
+    fn from(err: mongodb::error::Error) -> Self {
+        if let Some(message) = duplicate_key_message(&err) {
+            return match duplicate_key_field(message) {
+                Some(field) => ServiceError::AlreadyExists(field),
+                None => ServiceError::Conflict("Document already exists".to_string()),
+            };
+        }
+        if err.contains_label(TRANSIENT_TRANSACTION_ERROR) {
+            return ServiceError::TransientTransaction(err.to_string());
+        }
+        if matches!(*err.kind, ErrorKind::Io(_) | ErrorKind::ServerSelection { .. } | ErrorKind::ConnectionPoolCleared { .. }) {
+            return ServiceError::ServiceUnavailable(err.to_string());
+        }
+        ServiceError::DatabaseError(err.to_string())
+    }
+}
+
+impl From<mongodb::bson::ser::Error> for ServiceError {
+    fn from(err: mongodb::bson::ser::Error) -> Self {
+        ServiceError::InternalServerError(err.to_string())
+    }
+}
+
+impl From<mongodb::bson::de::Error> for ServiceError {
+    fn from(err: mongodb::bson::de::Error) -> Self {
+        ServiceError::InternalServerError(err.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for ServiceError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        ServiceError::InternalServerError(err.to_string())
+    }
+}
+
+fn duplicate_key_message(err: &mongodb::error::Error) -> Option<&str> {
+    match err.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(e)) if e.code == DUPLICATE_KEY_CODE => Some(&e.message),
+        ErrorKind::Command(e) if e.code == DUPLICATE_KEY_CODE => Some(&e.message),
+        ErrorKind::BulkWrite(e) => e
+            .write_errors
+            .as_ref()?
+            .iter()
+            .find(|e| e.code == DUPLICATE_KEY_CODE)
+            .map(|e| e.message.as_str()),
+        _ => None,
+    }
+}
+
+// Extract the field from a server message such as
+// `E11000 duplicate key error collection: db.users index: email_unique dup key: { email: "a@b.c" }`.
+// Servers older than 4.2 leave the key name empty, so fall back to the index name,
+// which very old servers prefix with the namespace (`db.users.$email_1`).
+fn duplicate_key_field(message: &str) -> Option<String> {
+    let from_key = message
+        .split_once("dup key: {")
+        .and_then(|(_, rest)| rest.split_once(':'))
+        .map(|(field, _)| field.trim().trim_matches('"').to_string())
+        .filter(|field| !field.is_empty());
+    from_key.or_else(|| {
+        let (_, rest) = message.split_once("index: ")?;
+        let index = rest.split_whitespace().next()?;
+        let index = index.rsplit_once('$').map_or(index, |(_, name)| name);
+        Some(index.rsplit_once('_').map_or(index, |(field, _)| field).to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_key_field_reads_the_key_name() {
+        let message = r#"E11000 duplicate key error collection: db.users index: email_unique dup key: { email: "a@b.c" }"#;
+        assert_eq!(duplicate_key_field(message).as_deref(), Some("email"));
+
+        let message = r#"E11000 duplicate key error collection: db.users index: profile.email_1 dup key: { "profile.email": "a:b" }"#;
+        assert_eq!(duplicate_key_field(message).as_deref(), Some("profile.email"));
+    }
+
+    #[test]
+    fn duplicate_key_field_falls_back_to_the_index_name() {
+        let message = r#"E11000 duplicate key error collection: db.users index: email_1 dup key: { : "a@b.c" }"#;
+        assert_eq!(duplicate_key_field(message).as_deref(), Some("email"));
+
+        let message = r#"E11000 duplicate key error index: db.users.$email_unique  dup key: { : "a@b.c" }"#;
+        assert_eq!(duplicate_key_field(message).as_deref(), Some("email"));
+
+        let message = r#"E11000 duplicate key error collection: db.users index: email dup key: { : "a@b.c" }"#;
+        assert_eq!(duplicate_key_field(message).as_deref(), Some("email"));
+    }
+
+    #[test]
+    fn duplicate_key_field_gives_up_on_unknown_messages() {
+        assert_eq!(duplicate_key_field("E11000 duplicate key error"), None);
+    }
+
+    #[test]
+    fn already_exists_exposes_the_field() {
+        let error = ServiceError::AlreadyExists("email".to_string()).to_graphql_error();
+        let extensions = error.extensions.unwrap();
+        assert_eq!(extensions.get("code"), Some(&async_graphql::Value::from("ALREADY_EXISTS")));
+        assert_eq!(extensions.get("field"), Some(&async_graphql::Value::from("email")));
+    }
+}
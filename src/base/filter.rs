@@ -0,0 +1,450 @@
+use async_graphql::{Enum, InputObject, ID};
+use mongodb::bson::{oid::ObjectId, Bson, Document, Regex};
+
+use crate::base::error::{ServiceError, ServiceResult};
+use crate::base::scalars::DateTime;
+use crate::constants::filter::MAX_REGEX_LENGTH;
+
+// Translates one field's operators into a MongoDB condition document,
+// e.g. `{ "$gte": 18, "$lt": 65 }`
+pub trait FieldFilter {
+    fn to_condition(&self) -> ServiceResult<Document>;
+}
+
+// Implemented by the per-model filter inputs generated with `model_filter!`
+pub trait ModelFilter {
+    fn to_document_at(&self, depth: usize) -> ServiceResult<Document>;
+
+    fn to_document(&self) -> ServiceResult<Document> {
+        self.to_document_at(0)
+    }
+}
+
+// Implemented by the per-model sort inputs generated with `model_sort!`
+pub trait ModelSort {
+    fn field_name(&self) -> &'static str;
+    fn direction(&self) -> SortDirection;
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Default)]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+// Build a sort document from the client's sort list, always ending with
+// `_id` so results are stable between identical queries
+pub fn sort_document<S: ModelSort>(sorts: &[S]) -> Document {
+    let mut sort = Document::new();
+    for item in sorts {
+        let order = match item.direction() {
+            SortDirection::Asc => 1,
+            SortDirection::Desc => -1,
+        };
+        if !sort.contains_key(item.field_name()) {
+            sort.insert(item.field_name(), order);
+        }
+    }
+    if !sort.contains_key("_id") {
+        sort.insert("_id", 1);
+    }
+    sort
+}
+
+// Combine clauses with `$and`, avoiding the wrapper for zero or one clause
+pub fn and_clauses(mut clauses: Vec<Document>) -> Document {
+    match clauses.len() {
+        0 => Document::new(),
+        1 => clauses.remove(0),
+        _ => mongodb::bson::doc! { "$and": clauses },
+    }
+}
+
+#[derive(InputObject, Default)]
+pub struct StringFilter {
+    pub eq: Option<String>,
+    pub ne: Option<String>,
+    #[graphql(name = "in")]
+    pub in_: Option<Vec<String>>,
+    pub nin: Option<Vec<String>>,
+    pub contains: Option<String>,
+    pub starts_with: Option<String>,
+    pub regex: Option<String>,
+    pub exists: Option<bool>,
+}
+
+impl FieldFilter for StringFilter {
+    fn to_condition(&self) -> ServiceResult<Document> {
+        let mut condition = Document::new();
+        insert_opt(&mut condition, "$eq", self.eq.clone());
+        insert_opt(&mut condition, "$ne", self.ne.clone());
+        insert_opt(&mut condition, "$in", self.in_.clone());
+        insert_opt(&mut condition, "$nin", self.nin.clone());
+        insert_opt(&mut condition, "$exists", self.exists);
+
+        // contains, startsWith and regex all compile to `$regex`; MongoDB only
+        // accepts one per field so they are combined through `$all`
+        let mut patterns = Vec::new();
+        if let Some(value) = &self.contains {
+            patterns.push(escape_regex(value));
+        }
+        if let Some(value) = &self.starts_with {
+            patterns.push(format!("^{}", escape_regex(value)));
+        }
+        if let Some(pattern) = &self.regex {
+            if pattern.len() > MAX_REGEX_LENGTH {
+                return Err(ServiceError::ValidationError(format!(
+                    "regex must be at most {} characters",
+                    MAX_REGEX_LENGTH
+                )));
+            }
+            patterns.push(pattern.clone());
+        }
+
+        let mut regexes: Vec<Bson> = patterns
+            .into_iter()
+            .map(|pattern| Bson::RegularExpression(Regex { pattern, options: String::new() }))
+            .collect();
+        match regexes.len() {
+            0 => {}
+            1 => {
+                condition.insert("$regex", regexes.remove(0));
+            }
+            _ => {
+                condition.insert("$all", regexes);
+            }
+        }
+
+        Ok(condition)
+    }
+}
+
+#[derive(InputObject, Default)]
+pub struct IntFilter {
+    pub eq: Option<i32>,
+    pub ne: Option<i32>,
+    #[graphql(name = "in")]
+    pub in_: Option<Vec<i32>>,
+    pub nin: Option<Vec<i32>>,
+    pub gt: Option<i32>,
+    pub gte: Option<i32>,
+    pub lt: Option<i32>,
+    pub lte: Option<i32>,
+    pub exists: Option<bool>,
+}
+
+impl FieldFilter for IntFilter {
+    fn to_condition(&self) -> ServiceResult<Document> {
+        let mut condition = Document::new();
+        insert_opt(&mut condition, "$eq", self.eq);
+        insert_opt(&mut condition, "$ne", self.ne);
+        insert_opt(&mut condition, "$in", self.in_.clone());
+        insert_opt(&mut condition, "$nin", self.nin.clone());
+        insert_opt(&mut condition, "$gt", self.gt);
+        insert_opt(&mut condition, "$gte", self.gte);
+        insert_opt(&mut condition, "$lt", self.lt);
+        insert_opt(&mut condition, "$lte", self.lte);
+        insert_opt(&mut condition, "$exists", self.exists);
+        Ok(condition)
+    }
+}
+
+#[derive(InputObject, Default)]
+pub struct IdFilter {
+    pub eq: Option<ID>,
+    pub ne: Option<ID>,
+    #[graphql(name = "in")]
+    pub in_: Option<Vec<ID>>,
+    pub nin: Option<Vec<ID>>,
+}
+
+impl FieldFilter for IdFilter {
+    fn to_condition(&self) -> ServiceResult<Document> {
+        let mut condition = Document::new();
+        insert_opt(&mut condition, "$eq", self.eq.as_ref().map(parse_id).transpose()?);
+        insert_opt(&mut condition, "$ne", self.ne.as_ref().map(parse_id).transpose()?);
+        insert_opt(&mut condition, "$in", self.in_.as_deref().map(parse_ids).transpose()?);
+        insert_opt(&mut condition, "$nin", self.nin.as_deref().map(parse_ids).transpose()?);
+        Ok(condition)
+    }
+}
+
+#[derive(InputObject, Default)]
+pub struct DateTimeFilter {
+    pub eq: Option<DateTime>,
+    pub ne: Option<DateTime>,
+    pub gt: Option<DateTime>,
+    pub gte: Option<DateTime>,
+    pub lt: Option<DateTime>,
+    pub lte: Option<DateTime>,
+    pub exists: Option<bool>,
+}
+
+impl FieldFilter for DateTimeFilter {
+    fn to_condition(&self) -> ServiceResult<Document> {
+        let mut condition = Document::new();
+        insert_opt(&mut condition, "$eq", self.eq);
+        insert_opt(&mut condition, "$ne", self.ne);
+        insert_opt(&mut condition, "$gt", self.gt);
+        insert_opt(&mut condition, "$gte", self.gte);
+        insert_opt(&mut condition, "$lt", self.lt);
+        insert_opt(&mut condition, "$lte", self.lte);
+        insert_opt(&mut condition, "$exists", self.exists);
+        Ok(condition)
+    }
+}
+
+fn parse_id(id: &ID) -> ServiceResult<ObjectId> {
+    ObjectId::parse_str(id.as_str()).map_err(|_| ServiceError::InvalidId(id.to_string()))
+}
+
+fn parse_ids(ids: &[ID]) -> ServiceResult<Vec<ObjectId>> {
+    ids.iter().map(parse_id).collect()
+}
+
+fn insert_opt<V: Into<Bson>>(document: &mut Document, key: &str, value: Option<V>) {
+    if let Some(value) = value {
+        document.insert(key, value);
+    }
+}
+
+// Escape user input so it is matched literally inside a regex
+pub fn escape_regex(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Generate a GraphQL filter input for a model.
+///
+/// Each entry maps a GraphQL field to its filter type and the document path
+/// it queries, so only the listed fields can ever reach MongoDB:
+///
+/// ```ignore
+/// model_filter!(UserFilterInput {
+///     id: IdFilter => "_id",
+///     name: StringFilter => "name",
+/// });
+/// ```
+#[macro_export]
+macro_rules! model_filter {
+    ($name:ident { $($field:ident : $filter:ty => $path:literal),* $(,)? }) => {
+        #[derive(async_graphql::InputObject, Default)]
+        pub struct $name {
+            $(pub $field: Option<$filter>,)*
+            pub and: Option<Vec<$name>>,
+            pub or: Option<Vec<$name>>,
+            pub not: Option<Box<$name>>,
+        }
+
+        impl $crate::base::filter::ModelFilter for $name {
+            fn to_document_at(
+                &self,
+                depth: usize,
+            ) -> $crate::base::error::ServiceResult<mongodb::bson::Document> {
+                use $crate::base::filter::FieldFilter;
+
+                if depth > $crate::constants::filter::MAX_FILTER_DEPTH {
+                    return Err($crate::base::error::ServiceError::ValidationError(
+                        "Filter is nested too deeply".to_string(),
+                    ));
+                }
+
+                let mut clauses = Vec::new();
+                $(
+                    if let Some(filter) = &self.$field {
+                        let condition = filter.to_condition()?;
+                        if !condition.is_empty() {
+                            let mut clause = mongodb::bson::Document::new();
+                            clause.insert($path, condition);
+                            clauses.push(clause);
+                        }
+                    }
+                )*
+
+                if let Some(filters) = &self.and {
+                    for filter in filters {
+                        let inner = filter.to_document_at(depth + 1)?;
+                        if !inner.is_empty() {
+                            clauses.push(inner);
+                        }
+                    }
+                }
+
+                if let Some(filters) = &self.or {
+                    let branches = filters
+                        .iter()
+                        .map(|filter| filter.to_document_at(depth + 1))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if !branches.is_empty() {
+                        clauses.push(mongodb::bson::doc! { "$or": branches });
+                    }
+                }
+
+                if let Some(filter) = &self.not {
+                    let inner = filter.to_document_at(depth + 1)?;
+                    if !inner.is_empty() {
+                        clauses.push(mongodb::bson::doc! { "$nor": [inner] });
+                    }
+                }
+
+                Ok($crate::base::filter::and_clauses(clauses))
+            }
+        }
+    };
+}
+
+/// Generate a GraphQL sort input and its whitelisted field enum for a model.
+///
+/// ```ignore
+/// model_sort!(UserSortInput, UserSortField {
+///     Name => "name",
+///     Age => "age",
+/// });
+/// ```
+#[macro_export]
+macro_rules! model_sort {
+    ($name:ident, $field_enum:ident { $($variant:ident => $path:literal),* $(,)? }) => {
+        #[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq)]
+        pub enum $field_enum {
+            $($variant,)*
+        }
+
+        #[derive(async_graphql::InputObject)]
+        pub struct $name {
+            pub field: $field_enum,
+            #[graphql(default)]
+            pub direction: $crate::base::filter::SortDirection,
+        }
+
+        impl $crate::base::filter::ModelSort for $name {
+            fn field_name(&self) -> &'static str {
+                match self.field {
+                    $($field_enum::$variant => $path,)*
+                }
+            }
+
+            fn direction(&self) -> $crate::base::filter::SortDirection {
+                self.direction
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::doc;
+
+    use super::*;
+    use crate::constants::filter::MAX_FILTER_DEPTH;
+
+    crate::model_filter!(TestFilterInput {
+        name: StringFilter => "name",
+        age: IntFilter => "age",
+    });
+
+    fn name_eq(value: &str) -> TestFilterInput {
+        TestFilterInput {
+            name: Some(StringFilter { eq: Some(value.to_string()), ..Default::default() }),
+            ..Default::default()
+        }
+    }
+
+    fn regex(pattern: &str) -> Bson {
+        Bson::RegularExpression(Regex { pattern: pattern.to_string(), options: String::new() })
+    }
+
+    #[test]
+    fn escape_regex_escapes_every_metacharacter() {
+        for c in "\\.+*?()|[]{}^$#&-~".chars() {
+            assert_eq!(escape_regex(&c.to_string()), format!("\\{}", c));
+        }
+        assert_eq!(escape_regex("a.b*(c)"), "a\\.b\\*\\(c\\)");
+        assert_eq!(escape_regex("plain text_123"), "plain text_123");
+    }
+
+    #[test]
+    fn contains_and_starts_with_match_literally() {
+        let filter = StringFilter { contains: Some("a.c".to_string()), ..Default::default() };
+        assert_eq!(filter.to_condition().unwrap(), doc! { "$regex": regex("a\\.c") });
+
+        let filter = StringFilter { starts_with: Some(".*".to_string()), ..Default::default() };
+        assert_eq!(filter.to_condition().unwrap(), doc! { "$regex": regex("^\\.\\*") });
+    }
+
+    #[test]
+    fn several_patterns_are_combined_with_all() {
+        let filter = StringFilter {
+            contains: Some("b".to_string()),
+            starts_with: Some("a".to_string()),
+            regex: Some("c$".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter.to_condition().unwrap(),
+            doc! { "$all": [regex("b"), regex("^a"), regex("c$")] }
+        );
+    }
+
+    #[test]
+    fn long_regex_is_rejected() {
+        let filter = StringFilter { regex: Some("a".repeat(MAX_REGEX_LENGTH + 1)), ..Default::default() };
+        assert!(matches!(filter.to_condition(), Err(ServiceError::ValidationError(_))));
+
+        let filter = StringFilter { regex: Some("a".repeat(MAX_REGEX_LENGTH)), ..Default::default() };
+        assert!(filter.to_condition().is_ok());
+    }
+
+    #[test]
+    fn fields_and_nested_operators_are_combined() {
+        let filter = TestFilterInput {
+            age: Some(IntFilter { gte: Some(18), ..Default::default() }),
+            and: Some(vec![name_eq("a")]),
+            or: Some(vec![name_eq("b"), name_eq("c")]),
+            not: Some(Box::new(name_eq("d"))),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter.to_document().unwrap(),
+            doc! {
+                "$and": [
+                    { "age": { "$gte": 18 } },
+                    { "name": { "$eq": "a" } },
+                    { "$or": [{ "name": { "$eq": "b" } }, { "name": { "$eq": "c" } }] },
+                    { "$nor": [{ "name": { "$eq": "d" } }] },
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn single_or_empty_clauses_are_not_wrapped() {
+        assert_eq!(TestFilterInput::default().to_document().unwrap(), doc! {});
+        assert_eq!(name_eq("a").to_document().unwrap(), doc! { "name": { "$eq": "a" } });
+
+        let filter = TestFilterInput { and: Some(vec![TestFilterInput::default()]), ..Default::default() };
+        assert_eq!(filter.to_document().unwrap(), doc! {});
+    }
+
+    #[test]
+    fn nesting_deeper_than_the_limit_is_rejected() {
+        fn nested(depth: usize) -> TestFilterInput {
+            (0..depth).fold(name_eq("a"), |inner, _| TestFilterInput {
+                not: Some(Box::new(inner)),
+                ..Default::default()
+            })
+        }
+
+        assert!(nested(MAX_FILTER_DEPTH).to_document().is_ok());
+        assert!(matches!(
+            nested(MAX_FILTER_DEPTH + 1).to_document(),
+            Err(ServiceError::ValidationError(_))
+        ));
+    }
+}
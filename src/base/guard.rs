@@ -0,0 +1,144 @@
+use async_graphql::{Context, Guard, Result, ID};
+
+use crate::base::auth::{CurrentGrants, CurrentUser, Permission, Role};
+use crate::base::error::ServiceError;
+
+// Resolve the caller with the roles and permissions stored now, see
+// CurrentGrants, or fail with AUTHENTICATION_ERROR
+async fn current_user(ctx: &Context<'_>) -> Result<CurrentUser> {
+    let unauthenticated = |message: &str| ServiceError::AuthenticationError(message.to_string()).to_graphql_error();
+    let user = ctx.data_opt::<CurrentUser>().ok_or_else(|| unauthenticated("Not authenticated"))?;
+    ctx.data::<CurrentGrants>()?
+        .refresh(user)
+        .await
+        .map_err(|e| e.to_graphql_error())?
+        .ok_or_else(|| unauthenticated("User no longer exists"))
+}
+
+fn forbidden(message: &str) -> async_graphql::Error {
+    ServiceError::AuthorizationError(message.to_string()).to_graphql_error()
+}
+
+// Allows callers having at least one of the given roles
+pub struct RoleGuard {
+    roles: Vec<Role>,
+}
+
+impl RoleGuard {
+    pub fn new(role: Role) -> Self {
+        Self { roles: vec![role] }
+    }
+
+    pub fn any(roles: Vec<Role>) -> Self {
+        Self { roles }
+    }
+}
+
+impl Guard for RoleGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        let user = current_user(ctx).await?;
+        if self.roles.iter().any(|role| user.has_role(*role)) {
+            Ok(())
+        } else {
+            Err(forbidden("Insufficient role"))
+        }
+    }
+}
+
+// Allows callers granted the permission directly or through one of their roles
+pub struct PermissionGuard {
+    permission: Permission,
+}
+
+impl PermissionGuard {
+    pub fn new(permission: Permission) -> Self {
+        Self { permission }
+    }
+}
+
+impl Guard for PermissionGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        let user = current_user(ctx).await?;
+        if user.has_permission(self.permission) {
+            Ok(())
+        } else {
+            Err(forbidden("Missing permission"))
+        }
+    }
+}
+
+// Allows the owner of a resource, or callers having the given role
+pub struct OwnerOrRole {
+    owner_id: Option<String>,
+    role: Role,
+}
+
+impl OwnerOrRole {
+    pub fn new(owner_id: Option<&ID>, role: Role) -> Self {
+        Self {
+            owner_id: owner_id.map(|id| id.to_string()),
+            role,
+        }
+    }
+}
+
+impl Guard for OwnerOrRole {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        let user = current_user(ctx).await?;
+        let is_owner = self.owner_id.as_deref() == Some(user.id.as_str());
+        if is_owner || user.has_role(self.role) {
+            Ok(())
+        } else {
+            Err(forbidden("Only the owner or an authorized role can access this resource"))
+        }
+    }
+}
+
+// Allows the owner of a resource, or callers having the given permission
+pub struct OwnerOrPermission {
+    owner_id: Option<String>,
+    permission: Permission,
+}
+
+impl OwnerOrPermission {
+    pub fn new(owner_id: Option<&ID>, permission: Permission) -> Self {
+        Self {
+            owner_id: owner_id.map(|id| id.to_string()),
+            permission,
+        }
+    }
+}
+
+impl Guard for OwnerOrPermission {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        let user = current_user(ctx).await?;
+        let is_owner = self.owner_id.as_deref() == Some(user.id.as_str());
+        if is_owner || user.has_permission(self.permission) {
+            Ok(())
+        } else {
+            Err(forbidden("Only the owner or a caller with the required permission can access this resource"))
+        }
+    }
+}
+
+// Applies `guard` only when `condition` holds, e.g. for arguments reserved to admins
+pub struct GuardIf<G> {
+    condition: bool,
+    guard: G,
+}
+
+impl<G: Guard> GuardIf<G> {
+    pub fn new(condition: bool, guard: G) -> Self {
+        Self { condition, guard }
+    }
+}
+
+impl<G: Guard + Send + Sync> Guard for GuardIf<G> {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        if self.condition {
+            self.guard.check(ctx).await
+        } else {
+            Ok(())
+        }
+    }
+}
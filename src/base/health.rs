@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::future::join_all;
+use mongodb::Database;
+use serde::Serialize;
+
+use crate::base::error::ServiceResult;
+use crate::db::connection;
+
+// A dependency the app can't serve requests without
+#[async_trait::async_trait]
+pub trait HealthCheck: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn check(&self) -> ServiceResult<()>;
+}
+
+pub struct MongoHealthCheck {
+    db: Database,
+}
+
+impl MongoHealthCheck {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthCheck for MongoHealthCheck {
+    fn name(&self) -> &str {
+        "mongodb"
+    }
+
+    async fn check(&self) -> ServiceResult<()> {
+        Ok(connection::ping(&self.db).await?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadinessStatus {
+    Ready,
+    Degraded,
+    ShuttingDown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyStatus {
+    pub status: CheckStatus,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessReport {
+    pub status: ReadinessStatus,
+    pub checks: BTreeMap<String, DependencyStatus>,
+}
+
+impl ReadinessReport {
+    pub fn is_ready(&self) -> bool {
+        self.status == ReadinessStatus::Ready
+    }
+}
+
+// Readiness of the app: every registered check must pass within the timeout,
+// and the app must not be shutting down. Clones share the shutdown flag, so
+// the shutdown handler can flip the copy the health router holds.
+#[derive(Clone)]
+pub struct Health {
+    checks: Vec<Arc<dyn HealthCheck>>,
+    timeout: Duration,
+    shutting_down: Arc<AtomicBool>,
+}
+
+impl Health {
+    pub fn new(timeout: Duration) -> Self {
+        Self { checks: Vec::new(), timeout, shutting_down: Arc::new(AtomicBool::new(false)) }
+    }
+
+    pub fn with_check(mut self, check: impl HealthCheck + 'static) -> Self {
+        self.checks.push(Arc::new(check));
+        self
+    }
+
+    // Report not ready from now on so load balancers stop routing here
+    // while in-flight requests drain
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    // Run every check concurrently, each bounded by the timeout
+    pub async fn readiness(&self) -> ReadinessReport {
+        let results = join_all(self.checks.iter().map(|check| async move {
+            let started = Instant::now();
+            let error = match tokio::time::timeout(self.timeout, check.check()).await {
+                Ok(Ok(())) => None,
+                Ok(Err(err)) => Some(err.to_string()),
+                Err(_) => Some(format!("No answer within {} ms", self.timeout.as_millis())),
+            };
+            let status = DependencyStatus {
+                status: if error.is_none() { CheckStatus::Up } else { CheckStatus::Down },
+                latency_ms: started.elapsed().as_millis() as u64,
+                error,
+            };
+            (check.name().to_string(), status)
+        }))
+        .await;
+
+        let checks = results.into_iter().collect::<BTreeMap<_, _>>();
+        let status = if self.is_shutting_down() {
+            ReadinessStatus::ShuttingDown
+        } else if checks.values().all(|check| check.status == CheckStatus::Up) {
+            ReadinessStatus::Ready
+        } else {
+            ReadinessStatus::Degraded
+        };
+        ReadinessReport { status, checks }
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use async_graphql::dataloader::{DataLoader, HashMapCache, Loader};
+use mongodb::bson::oid::ObjectId;
+use serde::de::DeserializeOwned;
+
+use crate::base::base_service::BaseService;
+use crate::base::error::ServiceError;
+
+// Batches the id lookups of any BaseService into one `$in` query. Wrapped in
+// a ModelLoader, every `load_one` made while resolving the same tick of a
+// request shares a single round trip, and repeated ids hit the cache.
+pub struct IdLoader<S, T> {
+    service: S,
+    _model: PhantomData<fn() -> T>,
+}
+
+impl<S, T> IdLoader<S, T> {
+    pub fn new(service: S) -> Self {
+        Self { service, _model: PhantomData }
+    }
+}
+
+impl<S, T> Loader<ObjectId> for IdLoader<S, T>
+where
+    S: BaseService<T> + Send + Sync + 'static,
+    T: DeserializeOwned + Clone + Unpin + Send + Sync + 'static,
+{
+    type Value = T;
+    type Error = ServiceError;
+
+    async fn load(&self, keys: &[ObjectId]) -> Result<HashMap<ObjectId, T>, ServiceError> {
+        self.service.find_by_ids(keys).await
+    }
+}
+
+// Loaders cache for their whole lifetime, so a new one is created for every
+// request, see graphql::loaders
+pub type ModelLoader<S, T> = DataLoader<IdLoader<S, T>, HashMapCache>;
+
+pub fn model_loader<S, T>(service: S) -> ModelLoader<S, T>
+where
+    S: BaseService<T> + Send + Sync + 'static,
+    T: DeserializeOwned + Clone + Unpin + Send + Sync + 'static,
+{
+    DataLoader::with_cache(IdLoader::new(service), tokio::spawn, HashMapCache::default())
+}
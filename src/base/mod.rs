@@ -5,7 +5,17 @@ pub mod crud_service;
 pub mod error;
 pub mod base_event;
 pub mod base_router;
+pub mod pagination;
+pub mod filter;
+pub mod auth;
+pub mod guard;
+pub mod validation;
+pub mod scalars;
+pub mod audit;
+pub mod loader;
+pub mod health;
+pub mod shutdown;
 
 // Re-export commonly used items
-pub use base_model::BaseModel;
+pub use base_model::{BaseModel, Status};
 pub use error::ServiceError;
@@ -0,0 +1,264 @@
+use async_graphql::connection::{Connection, Edge, OpaqueCursor};
+use async_graphql::{OutputType, SimpleObject};
+use futures::StreamExt;
+use mongodb::{
+    bson::{doc, from_document, oid::ObjectId, Document},
+    options::{FindOneOptions, FindOptions},
+    Collection,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::base::error::{ServiceError, ServiceResult};
+use crate::constants::pagination::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+
+// Cursor payload, encoded as base64 JSON so clients treat it as opaque
+#[derive(Serialize, Deserialize)]
+pub struct IdCursor {
+    pub id: String,
+}
+
+pub type Cursor = OpaqueCursor<IdCursor>;
+
+// Extra fields exposed on every connection type
+#[derive(SimpleObject)]
+pub struct ConnectionFields {
+    pub total_count: u64,
+}
+
+pub type ModelConnection<T> = Connection<Cursor, T, ConnectionFields>;
+
+// Relay pagination arguments after cursor decoding
+#[derive(Default)]
+pub struct PageArgs {
+    pub after: Option<ObjectId>,
+    pub before: Option<ObjectId>,
+    pub first: Option<usize>,
+    pub last: Option<usize>,
+    // Whether to count every matching document, only worth it when the
+    // client selected `totalCount`
+    pub count_total: bool,
+}
+
+impl PageArgs {
+    pub fn new(
+        after: Option<Cursor>,
+        before: Option<Cursor>,
+        first: Option<usize>,
+        last: Option<usize>,
+    ) -> ServiceResult<Self> {
+        Ok(Self {
+            after: after.map(|cursor| parse_cursor(&cursor)).transpose()?,
+            before: before.map(|cursor| parse_cursor(&cursor)).transpose()?,
+            first,
+            last,
+            count_total: false,
+        })
+    }
+
+    // Count the matching documents when `selected` holds, e.g.
+    // `ctx.look_ahead().field("totalCount").exists()`
+    pub fn with_total_count(mut self, selected: bool) -> Self {
+        self.count_total = selected;
+        self
+    }
+
+    // Documents per page: `first`, else `last`, else the default, capped
+    fn limit(&self) -> usize {
+        self.first.or(self.last).unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE)
+    }
+}
+
+// Number of items a list field returns for the requested `first`/`last`/`limit`,
+// clamped the same way `paginate` does
+pub fn page_size(requested: Option<i32>, default: usize) -> usize {
+    requested
+        .map_or(default, |size| usize::try_from(size).unwrap_or(0))
+        .min(MAX_PAGE_SIZE)
+}
+
+// Complexity of a list field: its selection is resolved once per item
+pub fn list_complexity(size: usize, child_complexity: usize) -> usize {
+    size.max(1).saturating_mul(child_complexity)
+}
+
+fn parse_cursor(cursor: &Cursor) -> ServiceResult<ObjectId> {
+    ObjectId::parse_str(&cursor.id).map_err(|_| ServiceError::InvalidId(cursor.id.clone()))
+}
+
+// One page of documents together with the data needed for pageInfo
+pub struct Page<T> {
+    pub items: Vec<(ObjectId, T)>,
+    pub has_previous_page: bool,
+    pub has_next_page: bool,
+    // 0 unless the page args asked for the count
+    pub total_count: u64,
+}
+
+impl<T: OutputType> Page<T> {
+    pub fn into_connection(self) -> ModelConnection<T> {
+        let mut connection = Connection::with_additional_fields(
+            self.has_previous_page,
+            self.has_next_page,
+            ConnectionFields { total_count: self.total_count },
+        );
+        connection.edges.extend(self.items.into_iter().map(|(id, node)| {
+            Edge::new(OpaqueCursor(IdCursor { id: id.to_hex() }), node)
+        }));
+        connection
+    }
+}
+
+// Keyset pagination over `_id`: the cursor bounds become range conditions on
+// `_id`, so no page ever needs skip or loads more than `limit + 1` documents.
+// The whole filter is only counted when `args.count_total` is set.
+pub async fn paginate<T>(
+    collection: &Collection<T>,
+    filter: Document,
+    args: PageArgs,
+) -> ServiceResult<Page<T>>
+where
+    T: DeserializeOwned + Unpin + Send + Sync,
+{
+    let collection = collection.clone_with_type::<Document>();
+    let backward = args.last.is_some() && args.first.is_none();
+    let limit = args.limit();
+
+    let mut id_range = Document::new();
+    if let Some(after) = args.after {
+        id_range.insert("$gt", after);
+    }
+    if let Some(before) = args.before {
+        id_range.insert("$lt", before);
+    }
+
+    let page_filter = with_id_condition(&filter, id_range);
+    let options = FindOptions::builder()
+        .sort(doc! { "_id": if backward { -1 } else { 1 } })
+        .limit((limit + 1) as i64)
+        .build();
+
+    let mut cursor = collection
+        .find(page_filter, options)
+        .await?;
+
+    let mut items = Vec::with_capacity(limit + 1);
+    while let Some(result) = cursor.next().await {
+        let document = result?;
+        let id = document
+            .get_object_id("_id")
+            .map_err(|e| ServiceError::InternalServerError(e.to_string()))?;
+        let node = from_document::<T>(document)?;
+        items.push((id, node));
+    }
+
+    let has_more = items.len() > limit;
+    items.truncate(limit);
+    if backward {
+        items.reverse();
+    }
+
+    // The side we paged away from only needs an existence check
+    let (has_previous_page, has_next_page) = if backward {
+        let has_next = match args.before {
+            Some(before) => exists(&collection, &filter, doc! { "$gte": before }).await?,
+            None => false,
+        };
+        (has_more, has_next)
+    } else {
+        let has_previous = match args.after {
+            Some(after) => exists(&collection, &filter, doc! { "$lte": after }).await?,
+            None => false,
+        };
+        (has_previous, has_more)
+    };
+
+    let total_count = if args.count_total {
+        collection.count_documents(filter, None).await?
+    } else {
+        0
+    };
+
+    Ok(Page {
+        items,
+        has_previous_page,
+        has_next_page,
+        total_count,
+    })
+}
+
+fn with_id_condition(filter: &Document, id_range: Document) -> Document {
+    if id_range.is_empty() {
+        return filter.clone();
+    }
+    if filter.is_empty() {
+        return doc! { "_id": id_range };
+    }
+    doc! { "$and": [filter.clone(), { "_id": id_range }] }
+}
+
+async fn exists(
+    collection: &Collection<Document>,
+    filter: &Document,
+    id_range: Document,
+) -> ServiceResult<bool> {
+    let options = FindOneOptions::builder().projection(doc! { "_id": 1 }).build();
+    let found = collection
+        .find_one(with_id_condition(filter, id_range), options)
+        .await?;
+    Ok(found.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::connection::CursorType;
+
+    use super::*;
+
+    fn cursor(id: &str) -> Cursor {
+        OpaqueCursor(IdCursor { id: id.to_string() })
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        assert!(Cursor::decode_cursor("not a cursor").is_err());
+
+        let result = PageArgs::new(Some(cursor("not-an-object-id")), None, Some(10), None);
+        assert!(matches!(result, Err(ServiceError::InvalidId(id)) if id == "not-an-object-id"));
+
+        let id = ObjectId::new();
+        let encoded = cursor(&id.to_hex()).encode_cursor();
+        let args = PageArgs::new(None, Some(Cursor::decode_cursor(&encoded).unwrap()), None, Some(10)).unwrap();
+        assert_eq!(args.before, Some(id));
+    }
+
+    #[test]
+    fn page_limit_is_clamped() {
+        let args = |first, last| PageArgs { first, last, ..Default::default() };
+        assert_eq!(args(None, None).limit(), DEFAULT_PAGE_SIZE);
+        assert_eq!(args(Some(5), Some(50)).limit(), 5);
+        assert_eq!(args(None, Some(50)).limit(), 50);
+        assert_eq!(args(Some(MAX_PAGE_SIZE + 1), None).limit(), MAX_PAGE_SIZE);
+        assert_eq!(args(None, Some(usize::MAX)).limit(), MAX_PAGE_SIZE);
+        assert_eq!(args(Some(0), None).limit(), 0);
+    }
+
+    #[test]
+    fn page_size_clamps_out_of_range_requests() {
+        assert_eq!(page_size(None, DEFAULT_PAGE_SIZE), DEFAULT_PAGE_SIZE);
+        assert_eq!(page_size(Some(7), DEFAULT_PAGE_SIZE), 7);
+        assert_eq!(page_size(Some(-3), DEFAULT_PAGE_SIZE), 0);
+        assert_eq!(page_size(Some(i32::MAX), DEFAULT_PAGE_SIZE), MAX_PAGE_SIZE);
+        assert_eq!(list_complexity(0, 3), 3);
+    }
+
+    #[test]
+    fn id_range_is_combined_with_the_filter() {
+        let id = ObjectId::new();
+        assert_eq!(with_id_condition(&doc! {}, doc! { "$gt": id }), doc! { "_id": { "$gt": id } });
+        assert_eq!(with_id_condition(&doc! { "age": 3 }, Document::new()), doc! { "age": 3 });
+        assert_eq!(
+            with_id_condition(&doc! { "age": 3 }, doc! { "$lt": id }),
+            doc! { "$and": [{ "age": 3 }, { "_id": { "$lt": id } }] },
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use async_graphql::{InputValueError, InputValueResult, Scalar, ScalarType, Value};
+use mongodb::bson::{self, Bson};
+use serde::{Deserialize, Serialize};
+
+// Timestamp stored as a BSON date and exchanged in GraphQL as an RFC 3339
+// string, e.g. "2024-05-01T09:30:00.000Z"
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DateTime(pub bson::DateTime);
+
+impl DateTime {
+    pub fn now() -> Self {
+        Self(bson::DateTime::now())
+    }
+}
+
+#[Scalar]
+impl ScalarType for DateTime {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match &value {
+            Value::String(s) => bson::DateTime::parse_rfc3339_str(s)
+                .map(DateTime)
+                .map_err(|_| InputValueError::custom(format!("invalid RFC 3339 date: {}", s))),
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        match self.0.try_to_rfc3339_string() {
+            Ok(s) => Value::String(s),
+            Err(_) => Value::Null,
+        }
+    }
+}
+
+impl From<bson::DateTime> for DateTime {
+    fn from(value: bson::DateTime) -> Self {
+        Self(value)
+    }
+}
+
+impl From<DateTime> for Bson {
+    fn from(value: DateTime) -> Self {
+        Bson::DateTime(value.0)
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::{watch, Notify};
+
+// Graceful shutdown coordination. `trigger` tells long-lived connections to
+// wind down; they hold a `ConnectionGuard` from `track` so the server can wait
+// for them with `drained`. Plain HTTP requests are drained by axum itself.
+#[derive(Clone)]
+pub struct Shutdown {
+    inner: Arc<ShutdownState>,
+}
+
+struct ShutdownState {
+    triggered: watch::Sender<bool>,
+    active: AtomicUsize,
+    idle: Notify,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(ShutdownState {
+                triggered: watch::Sender::new(false),
+                active: AtomicUsize::new(0),
+                idle: Notify::new(),
+            }),
+        }
+    }
+
+    pub fn trigger(&self) {
+        self.inner.triggered.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.inner.triggered.borrow()
+    }
+
+    // Resolves once shutdown is triggered, right away if it already was
+    pub async fn triggered(&self) {
+        let mut receiver = self.inner.triggered.subscribe();
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+
+    // Count a connection as active until the guard is dropped
+    pub fn track(&self) -> ConnectionGuard {
+        self.inner.active.fetch_add(1, Ordering::SeqCst);
+        ConnectionGuard { shutdown: self.clone() }
+    }
+
+    pub fn active_connections(&self) -> usize {
+        self.inner.active.load(Ordering::SeqCst)
+    }
+
+    // Resolves once no tracked connection is left
+    pub async fn drained(&self) {
+        loop {
+            // Registered before the check so a guard dropped in between still wakes us
+            let idle = self.inner.idle.notified();
+            if self.active_connections() == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
+
+pub struct ConnectionGuard {
+    shutdown: Shutdown,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        if self.shutdown.inner.active.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.shutdown.inner.idle.notify_waiters();
+        }
+    }
+}
+
+// Resolves on SIGTERM (sent by orchestrators on deploy) or SIGINT (Ctrl-C)
+pub async fn termination_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(e) => {
+                eprintln!("Cannot listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
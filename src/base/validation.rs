@@ -0,0 +1,271 @@
+use std::future::Future;
+
+use serde::Serialize;
+
+use crate::base::error::{ServiceError, ServiceResult};
+
+// One failed rule, reported to clients in `extensions.fieldErrors`
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+// Implemented by input objects to declare their synchronous rules, e.g.
+//
+//     v.string("email", &self.email).not_blank().max_length(254).email();
+//     v.int("age", &self.age).range(0, 150);
+//
+// Checks that need I/O are added by the caller with `Validation::check`.
+pub trait Validate {
+    fn validate(&self, v: &mut Validation);
+}
+
+// Collects field errors so that every invalid field is reported at once.
+// Only the first failing rule of a field is kept.
+#[derive(Default)]
+pub struct Validation {
+    errors: Vec<FieldError>,
+}
+
+impl Validation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Run the declared rules of an input
+    pub fn of<T: Validate + ?Sized>(input: &T) -> Self {
+        let mut v = Self::new();
+        input.validate(&mut v);
+        v
+    }
+
+    pub fn string<'a, S: StrValue + ?Sized>(&'a mut self, field: &str, value: &'a S) -> StrRules<'a> {
+        StrRules { field: self.rules(field), value: value.as_opt_str() }
+    }
+
+    pub fn int<'a, N: IntValue + ?Sized>(&'a mut self, field: &str, value: &N) -> IntRules<'a> {
+        IntRules { field: self.rules(field), value: value.as_opt_i64() }
+    }
+
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        if !self.has_error(field) {
+            self.errors.push(FieldError { field: field.to_string(), message: message.into() });
+        }
+    }
+
+    pub fn has_error(&self, field: &str) -> bool {
+        self.errors.iter().any(|e| e.field == field)
+    }
+
+    // Async validator: `check` resolves to whether the value is acceptable. It is
+    // skipped when the field already failed a cheaper rule.
+    pub async fn check<F>(&mut self, field: &str, message: &str, check: F) -> ServiceResult<()>
+    where
+        F: Future<Output = ServiceResult<bool>>,
+    {
+        if !self.has_error(field) && !check.await? {
+            self.add(field, message);
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> ServiceResult<()> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ServiceError::InvalidInput(self.errors))
+        }
+    }
+
+    fn rules<'a>(&'a mut self, field: &str) -> FieldRules<'a> {
+        let failed = self.has_error(field);
+        FieldRules { validation: self, field: field.to_string(), failed }
+    }
+}
+
+struct FieldRules<'a> {
+    validation: &'a mut Validation,
+    field: String,
+    failed: bool,
+}
+
+impl FieldRules<'_> {
+    fn ensure(&mut self, ok: bool, message: impl FnOnce() -> String) {
+        if !self.failed && !ok {
+            self.validation.add(&self.field, message());
+            self.failed = true;
+        }
+    }
+}
+
+// Rules for string fields. Absent optional values pass every rule.
+pub struct StrRules<'a> {
+    field: FieldRules<'a>,
+    value: Option<&'a str>,
+}
+
+impl StrRules<'_> {
+    pub fn not_blank(mut self) -> Self {
+        let ok = self.value.is_none_or(|value| !value.trim().is_empty());
+        self.field.ensure(ok, || "must not be blank".to_string());
+        self
+    }
+
+    pub fn min_length(mut self, min: usize) -> Self {
+        let ok = self.value.is_none_or(|value| value.chars().count() >= min);
+        self.field.ensure(ok, || format!("must be at least {} characters", min));
+        self
+    }
+
+    pub fn max_length(mut self, max: usize) -> Self {
+        let ok = self.value.is_none_or(|value| value.chars().count() <= max);
+        self.field.ensure(ok, || format!("must be at most {} characters", max));
+        self
+    }
+
+    pub fn email(mut self) -> Self {
+        let ok = self.value.is_none_or(is_email);
+        self.field.ensure(ok, || "must be a valid email address".to_string());
+        self
+    }
+}
+
+// Rules for integer fields. Absent optional values pass.
+pub struct IntRules<'a> {
+    field: FieldRules<'a>,
+    value: Option<i64>,
+}
+
+impl IntRules<'_> {
+    pub fn min(mut self, min: i64) -> Self {
+        let ok = self.value.is_none_or(|value| value >= min);
+        self.field.ensure(ok, || format!("must be at least {}", min));
+        self
+    }
+
+    pub fn range(mut self, min: i64, max: i64) -> Self {
+        let ok = self.value.is_none_or(|value| (min..=max).contains(&value));
+        self.field.ensure(ok, || format!("must be between {} and {}", min, max));
+        self
+    }
+}
+
+pub trait StrValue {
+    fn as_opt_str(&self) -> Option<&str>;
+}
+
+impl StrValue for str {
+    fn as_opt_str(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl StrValue for String {
+    fn as_opt_str(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl StrValue for Option<String> {
+    fn as_opt_str(&self) -> Option<&str> {
+        self.as_deref()
+    }
+}
+
+pub trait IntValue {
+    fn as_opt_i64(&self) -> Option<i64>;
+}
+
+impl IntValue for i32 {
+    fn as_opt_i64(&self) -> Option<i64> {
+        Some(i64::from(*self))
+    }
+}
+
+impl IntValue for Option<i32> {
+    fn as_opt_i64(&self) -> Option<i64> {
+        self.map(i64::from)
+    }
+}
+
+// Pragmatic shape check: one `@`, a non-empty local part and a dotted domain
+fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && !value.chars().any(char::is_whitespace)
+        && domain.split('.').count() >= 2
+        && domain.split('.').all(|label| !label.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field_errors(v: Validation) -> Vec<(String, String)> {
+        match v.finish() {
+            Ok(()) => Vec::new(),
+            Err(ServiceError::InvalidInput(errors)) => errors.into_iter().map(|e| (e.field, e.message)).collect(),
+            Err(other) => panic!("unexpected error {}", other),
+        }
+    }
+
+    #[test]
+    fn every_field_is_reported_with_its_first_failure() {
+        let mut v = Validation::new();
+        v.string("name", " ").not_blank().min_length(3);
+        v.string("email", "nope").max_length(254).email();
+        v.int("age", &200).min(0).range(0, 150);
+        v.int("limit", &0).min(1);
+
+        assert_eq!(
+            field_errors(v),
+            vec![
+                ("name".to_string(), "must not be blank".to_string()),
+                ("email".to_string(), "must be a valid email address".to_string()),
+                ("age".to_string(), "must be between 0 and 150".to_string()),
+                ("limit".to_string(), "must be at least 1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn absent_optional_values_pass() {
+        let mut v = Validation::new();
+        v.string("email", &None::<String>).not_blank().email();
+        v.int("age", &None::<i32>).min(1).range(1, 2);
+        assert!(v.finish().is_ok());
+    }
+
+    #[test]
+    fn length_counts_characters() {
+        let mut v = Validation::new();
+        v.string("name", "Đỗ").min_length(2).max_length(2);
+        assert!(v.finish().is_ok());
+    }
+
+    #[tokio::test]
+    async fn async_checks_run_only_on_valid_fields() {
+        let mut v = Validation::new();
+        v.string("email", "a@b").email();
+        v.check("email", "is already taken", async { panic!("must not run") }).await.unwrap();
+        v.check("name", "is already taken", async { Ok(false) }).await.unwrap();
+
+        let errors = field_errors(v);
+        assert_eq!(errors[0].1, "must be a valid email address");
+        assert_eq!(errors[1], ("name".to_string(), "is already taken".to_string()));
+    }
+
+    #[test]
+    fn email_shape() {
+        for valid in ["a@b.co", "first.last+tag@mail.example.com"] {
+            assert!(is_email(valid), "{}", valid);
+        }
+        for invalid in ["", "a", "@b.co", "a@b", "a@b.", "a@.co", "a@b@c.co", "a b@c.co"] {
+            assert!(!is_email(invalid), "{}", invalid);
+        }
+    }
+}
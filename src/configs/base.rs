@@ -1,6 +1,10 @@
 //! Cấu hình cơ bản cho tất cả môi trường
 
+use std::convert::Infallible;
 use std::env;
+use std::str::FromStr;
+
+use crate::constants::jwt::DEV_JWT_SECRET;
 
 /// Cấu trúc chứa các cấu hình cơ bản
 #[derive(Debug, Clone)]
@@ -15,6 +19,51 @@ pub struct BaseConfig {
     pub mongo_db: String,
     /// Môi trường hiện tại
     pub environment: Environment,
+    /// Bật change stream để phát sự kiện cho mọi thay đổi trong MongoDB (yêu cầu replica set)
+    pub change_streams_enabled: bool,
+    /// Các collection được theo dõi bằng change stream
+    pub change_stream_collections: Vec<String>,
+    /// Gói mỗi mutation trong một transaction MongoDB (yêu cầu replica set)
+    pub unit_of_work_enabled: bool,
+    /// Độ sâu lồng nhau tối đa của một query GraphQL
+    pub max_depth: usize,
+    /// Độ phức tạp tối đa của một query, field danh sách được nhân theo `first`/`limit`
+    pub max_complexity: usize,
+    /// Số alias tối đa trong một query
+    pub max_aliases: usize,
+    /// Độ sâu đệ quy tối đa khi phân tích query (bao gồm cả fragment lồng nhau)
+    pub max_recursion: usize,
+    /// Cho phép introspection schema với mọi client
+    pub introspection_enabled: bool,
+    /// Phục vụ trang GraphiQL tại GET /graphql
+    pub graphiql_enabled: bool,
+    /// Secret trong header `x-introspection-secret` cho phép introspection khi đã tắt
+    pub introspection_secret: Option<String>,
+    /// Bật Automatic Persisted Queries (APQ) theo giao thức của Apollo
+    pub apq_enabled: bool,
+    /// Nơi lưu persisted query: `memory` (LRU trong tiến trình) hoặc `mongodb`
+    pub apq_store: String,
+    /// Số query tối đa giữ trong LRU cache
+    pub apq_cache_size: usize,
+    /// Chỉ chạy các query đã đăng ký trước, từ chối mọi query khác (trừ admin)
+    pub apq_allowlist_only: bool,
+    /// Thuật toán ký JWT: HS256 hoặc RS256
+    pub jwt_algorithm: String,
+    /// Secret dùng cho HS256
+    pub jwt_secret: String,
+    /// Đường dẫn private key PEM dùng cho RS256
+    pub jwt_private_key_path: Option<String>,
+    /// Đường dẫn public key PEM dùng cho RS256
+    pub jwt_public_key_path: Option<String>,
+    /// Thời gian tối đa (seconds) chờ request và subscription đang chạy kết thúc khi tắt server
+    pub shutdown_timeout: u64,
+    /// Thời gian (seconds) giữa lúc readiness báo `shutting_down` và lúc ngừng nhận kết nối mới,
+    /// để load balancer kịp ngừng gửi request tới instance này
+    pub shutdown_delay: u64,
+    /// Thời gian sống của access token (seconds)
+    pub access_token_ttl: u64,
+    /// Thời gian sống của refresh token (seconds)
+    pub refresh_token_ttl: u64,
 }
 
 /// Enum đại diện cho các môi trường khác nhau
@@ -25,14 +74,16 @@ pub enum Environment {
     Testing,
 }
 
-impl Environment {
+impl FromStr for Environment {
+    type Err = Infallible;
+
     /// Chuyển đổi từ string sang enum Environment
-    pub fn from_str(env_str: &str) -> Self {
-        match env_str.to_lowercase().as_str() {
+    fn from_str(env_str: &str) -> Result<Self, Self::Err> {
+        Ok(match env_str.to_lowercase().as_str() {
             "production" => Environment::Production,
             "testing" => Environment::Testing,
             _ => Environment::Development,
-        }
+        })
     }
 }
 
@@ -47,7 +98,67 @@ impl Default for BaseConfig {
             host: "127.0.0.1".to_string(),
             mongo_uri: "mongodb://localhost:27017".to_string(),
             mongo_db: "graphql_rust_db".to_string(),
-            environment: Environment::from_str(&environment),
+            environment: environment.parse().unwrap_or(Environment::Development),
+            change_streams_enabled: env_bool("CHANGE_STREAMS_ENABLED", false),
+            change_stream_collections: env_list("CHANGE_STREAM_COLLECTIONS", &["users"]),
+            unit_of_work_enabled: env_bool("UNIT_OF_WORK_ENABLED", false),
+            max_depth: env_usize("GRAPHQL_MAX_DEPTH", 15),
+            max_complexity: env_usize("GRAPHQL_MAX_COMPLEXITY", 5000),
+            max_aliases: env_usize("GRAPHQL_MAX_ALIASES", 50),
+            max_recursion: env_usize("GRAPHQL_MAX_RECURSION", 32),
+            introspection_enabled: env_bool("GRAPHQL_INTROSPECTION", true),
+            graphiql_enabled: env_bool("GRAPHIQL_ENABLED", true),
+            introspection_secret: env::var("GRAPHQL_INTROSPECTION_SECRET").ok().filter(|secret| !secret.is_empty()),
+            apq_enabled: env_bool("APQ_ENABLED", true),
+            apq_store: env::var("APQ_STORE").unwrap_or_else(|_| "memory".to_string()),
+            apq_cache_size: env_usize("APQ_CACHE_SIZE", 1000),
+            apq_allowlist_only: env_bool("APQ_ALLOWLIST_ONLY", false),
+            jwt_algorithm: env::var("JWT_ALGORITHM").unwrap_or_else(|_| "HS256".to_string()),
+            jwt_secret: env::var("JWT_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty())
+                .unwrap_or_else(|| DEV_JWT_SECRET.to_string()),
+            jwt_private_key_path: env::var("JWT_PRIVATE_KEY_PATH").ok(),
+            jwt_public_key_path: env::var("JWT_PUBLIC_KEY_PATH").ok(),
+            shutdown_timeout: env_u64("SHUTDOWN_TIMEOUT", 30),
+            shutdown_delay: env_u64("SHUTDOWN_DELAY", 0),
+            access_token_ttl: env_u64("JWT_ACCESS_TTL", 15 * 60), // 15 phút
+            refresh_token_ttl: env_u64("JWT_REFRESH_TTL", 7 * 24 * 3600), // 7 ngày
         }
     }
 }
+
+/// Đọc biến môi trường kiểu bool ("true"/"1"/"yes"), dùng giá trị mặc định nếu không có
+pub(crate) fn env_bool(key: &str, default: bool) -> bool {
+    env::var(key)
+        .map(|value| matches!(value.to_lowercase().as_str(), "true" | "1" | "yes"))
+        .unwrap_or(default)
+}
+
+/// Đọc biến môi trường kiểu số nguyên không âm
+pub(crate) fn env_u64(key: &str, default: u64) -> u64 {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Đọc biến môi trường kiểu usize
+pub(crate) fn env_usize(key: &str, default: usize) -> usize {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Đọc biến môi trường dạng danh sách phân tách bằng dấu phẩy
+pub(crate) fn env_list(key: &str, default: &[&str]) -> Vec<String> {
+    match env::var(key) {
+        Ok(value) => value
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect(),
+        Err(_) => default.iter().map(|item| item.to_string()).collect(),
+    }
+}
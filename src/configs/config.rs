@@ -36,7 +36,7 @@ impl AppConfig {
         let environment = env::var("RUST_ENV")
             .unwrap_or_else(|_| "development".to_string());
         
-        match Environment::from_str(&environment) {
+        match environment.parse().unwrap_or(Environment::Development) {
             Environment::Development => AppConfig::Development(DevelopmentConfig::default()),
             Environment::Production => AppConfig::Production(ProductionConfig::default()),
             Environment::Testing => AppConfig::Testing(TestingConfig::default()),
@@ -63,6 +63,102 @@ impl AppConfig {
         &self.base().mongo_db
     }
     
+    /// Kiểm tra xem change stream có được bật không
+    pub fn change_streams_enabled(&self) -> bool {
+        self.base().change_streams_enabled
+    }
+    
+    /// Lấy danh sách collection được theo dõi bằng change stream
+    pub fn change_stream_collections(&self) -> &[String] {
+        &self.base().change_stream_collections
+    }
+    
+    /// Kiểm tra xem mỗi mutation có được chạy trong một transaction không
+    pub fn unit_of_work_enabled(&self) -> bool {
+        self.base().unit_of_work_enabled
+    }
+    
+    /// Lấy độ sâu tối đa của query
+    pub fn max_depth(&self) -> usize {
+        self.base().max_depth
+    }
+    
+    /// Lấy độ phức tạp tối đa của query
+    pub fn max_complexity(&self) -> usize {
+        self.base().max_complexity
+    }
+    
+    /// Lấy số alias tối đa của query
+    pub fn max_aliases(&self) -> usize {
+        self.base().max_aliases
+    }
+    
+    /// Lấy độ sâu đệ quy tối đa khi phân tích query
+    pub fn max_recursion(&self) -> usize {
+        self.base().max_recursion
+    }
+    
+    /// Introspection có được mở cho mọi client không
+    pub fn introspection_enabled(&self) -> bool {
+        self.base().introspection_enabled
+    }
+    
+    /// Có phục vụ trang GraphiQL không
+    pub fn graphiql_enabled(&self) -> bool {
+        self.base().graphiql_enabled
+    }
+    
+    /// Secret cho phép introspection khi đã tắt
+    pub fn introspection_secret(&self) -> Option<&str> {
+        self.base().introspection_secret.as_deref()
+    }
+    
+    /// Có hỗ trợ Automatic Persisted Queries không
+    pub fn apq_enabled(&self) -> bool {
+        self.base().apq_enabled
+    }
+    
+    /// Lấy loại store của persisted query
+    pub fn apq_store(&self) -> &str {
+        &self.base().apq_store
+    }
+    
+    /// Lấy kích thước LRU cache của persisted query
+    pub fn apq_cache_size(&self) -> usize {
+        self.base().apq_cache_size
+    }
+    
+    /// Có chỉ cho phép các query đã đăng ký không
+    pub fn apq_allowlist_only(&self) -> bool {
+        self.base().apq_allowlist_only
+    }
+    
+    /// Lấy thời gian tối đa chờ drain khi tắt server
+    pub fn shutdown_timeout(&self) -> u64 {
+        self.base().shutdown_timeout
+    }
+    
+    /// Lấy thời gian chờ giữa lúc báo not-ready và lúc ngừng nhận kết nối
+    pub fn shutdown_delay(&self) -> u64 {
+        self.base().shutdown_delay
+    }
+    
+    /// Có cache response của query không (chỉ bật ở production)
+    pub fn cache_enabled(&self) -> bool {
+        match self {
+            AppConfig::Production(config) => config.enable_cache,
+            _ => false,
+        }
+    }
+    
+    /// Lấy thời gian tối đa (seconds) một response được giữ trong cache
+    pub fn cache_expiration(&self) -> u64 {
+        match self {
+            AppConfig::Production(config) => config.cache_expiration,
+            _ => 0,
+        }
+    }
+    
     /// Kiểm tra xem có đang ở môi trường development không
     pub fn is_development(&self) -> bool {
         matches!(self, AppConfig::Development(_))
@@ -79,6 +175,12 @@ impl AppConfig {
     }
 }
 
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Singleton để chỉ tạo cấu hình một lần
 static APP_CONFIG: OnceLock<AppConfig> = OnceLock::new();
 
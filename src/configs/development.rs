@@ -13,11 +13,12 @@ pub struct DevelopmentConfig {
 
 impl Default for DevelopmentConfig {
     fn default() -> Self {
-        let mut base = BaseConfig::default();
-        
         // Ghi đè các giá trị mặc định cho môi trường development
-        base.environment = Environment::Development;
-        base.mongo_db = "graphql_rust_dev_db".to_string();
+        let base = BaseConfig {
+            environment: Environment::Development,
+            mongo_db: "graphql_rust_dev_db".to_string(),
+            ..BaseConfig::default()
+        };
         
         Self {
             base,
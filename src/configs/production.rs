@@ -1,7 +1,7 @@
 //! Cấu hình cho môi trường sản phẩm
 
 use std::env;
-use crate::configs::base::{BaseConfig, Environment};
+use crate::configs::base::{env_bool, env_u64, env_usize, BaseConfig, Environment};
 
 /// Cấu trúc chứa các cấu hình cho môi trường sản phẩm
 #[derive(Debug, Clone)]
@@ -16,24 +16,35 @@ pub struct ProductionConfig {
 
 impl Default for ProductionConfig {
     fn default() -> Self {
-        let mut base = BaseConfig::default();
-        
         // Ghi đè các giá trị mặc định cho môi trường production
-        base.environment = Environment::Production;
-        base.host = "0.0.0.0".to_string(); // Bind to all interfaces in production
-        base.port = env::var("PORT")
-            .ok()
-            .and_then(|port| port.parse().ok())
-            .unwrap_or(4000);
-        base.mongo_uri = env::var("MONGODB_URI")
-            .unwrap_or_else(|_| "mongodb://localhost:27017".to_string());
-        base.mongo_db = env::var("MONGODB_DB")
-            .unwrap_or_else(|_| "graphql_rust_prod_db".to_string());
+        let base = BaseConfig {
+            environment: Environment::Production,
+            host: "0.0.0.0".to_string(), // Bind to all interfaces in production
+            port: env::var("PORT")
+                .ok()
+                .and_then(|port| port.parse().ok())
+                .unwrap_or(4000),
+            mongo_uri: env::var("MONGODB_URI")
+                .unwrap_or_else(|_| "mongodb://localhost:27017".to_string()),
+            mongo_db: env::var("MONGODB_DB")
+                .unwrap_or_else(|_| "graphql_rust_prod_db".to_string()),
+            // Giới hạn query chặt hơn ở production
+            max_depth: env_usize("GRAPHQL_MAX_DEPTH", 10),
+            max_complexity: env_usize("GRAPHQL_MAX_COMPLEXITY", 2000),
+            max_aliases: env_usize("GRAPHQL_MAX_ALIASES", 20),
+            max_recursion: env_usize("GRAPHQL_MAX_RECURSION", 16),
+            // Không công khai schema và IDE ở production, admin vẫn được introspection
+            introspection_enabled: env_bool("GRAPHQL_INTROSPECTION", false),
+            graphiql_enabled: env_bool("GRAPHIQL_ENABLED", false),
+            // Chờ load balancer thấy readiness đổi trạng thái trước khi đóng listener
+            shutdown_delay: env_u64("SHUTDOWN_DELAY", 5),
+            ..BaseConfig::default()
+        };
         
         Self {
             base,
-            enable_cache: true,
-            cache_expiration: 3600, // 1 hour
+            enable_cache: env_bool("CACHE_ENABLED", true),
+            cache_expiration: env_u64("CACHE_EXPIRATION", 3600), // 1 hour
         }
     }
 }
@@ -13,11 +13,12 @@ pub struct TestingConfig {
 
 impl Default for TestingConfig {
     fn default() -> Self {
-        let mut base = BaseConfig::default();
-        
         // Ghi đè các giá trị mặc định cho môi trường testing
-        base.environment = Environment::Testing;
-        base.mongo_db = "graphql_rust_test_db".to_string();
+        let base = BaseConfig {
+            environment: Environment::Testing,
+            mongo_db: "graphql_rust_test_db".to_string(),
+            ..BaseConfig::default()
+        };
         
         Self {
             base,
@@ -0,0 +1,20 @@
+//! Hằng số dùng cho audit log
+
+/// Collection lưu lịch sử thay đổi
+pub const AUDIT_COLLECTION: &str = "audit_logs";
+
+/// Các field không bao giờ được ghi vào audit log, giá trị sẽ được thay bằng `AUDIT_REDACTED`
+pub const AUDIT_REDACTED_FIELDS: &[&str] = &["password_hash"];
+
+/// Giá trị thay thế cho các field bị ẩn
+pub const AUDIT_REDACTED: &str = "[redacted]";
+
+/// Các field thay đổi ở mọi lần ghi nên không được đưa vào diff
+pub const AUDIT_IGNORED_FIELDS: &[&str] = &["_id", "updated_at", "version"];
+
+/// Số document được ghi và chụp snapshot mỗi lượt khi cập nhật hoặc xoá hàng loạt có audit,
+/// giới hạn bộ nhớ dùng cho snapshot
+pub const AUDIT_BATCH_SIZE: i64 = 500;
+
+/// Độ dài tối đa của header `x-request-id` nhận từ client
+pub const REQUEST_ID_MAX_LENGTH: usize = 128;
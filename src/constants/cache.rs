@@ -0,0 +1,4 @@
+//! Hằng số dùng cho cache response GraphQL
+
+/// Số response tối đa được giữ trong cache, response ít dùng nhất bị loại trước
+pub const RESPONSE_CACHE_CAPACITY: usize = 1000;
@@ -0,0 +1,7 @@
+//! Hằng số dùng cho hệ thống sự kiện
+
+/// Số sự kiện tối đa được giữ trong mỗi broadcast channel trước khi receiver chậm bị bỏ lại
+pub const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Thời gian chờ (giây) trước khi mở lại change stream bị lỗi
+pub const CHANGE_STREAM_RETRY_SECONDS: u64 = 5;
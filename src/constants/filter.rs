@@ -0,0 +1,10 @@
+//! Hằng số dùng cho bộ lọc truy vấn
+
+/// Độ sâu lồng tối đa của các toán tử `and`/`or`/`not`
+pub const MAX_FILTER_DEPTH: usize = 5;
+
+/// Độ dài tối đa của biểu thức `regex` do client gửi lên
+pub const MAX_REGEX_LENGTH: usize = 256;
+
+/// Số user được xoá mỗi lượt khi `deleteUsers` xoá theo bộ lọc
+pub const BULK_DELETE_BATCH_SIZE: i64 = 500;
@@ -0,0 +1,4 @@
+//! Hằng số dùng cho health check
+
+/// Thời gian tối đa (milliseconds) chờ một dependency trả lời khi kiểm tra readiness
+pub const HEALTH_CHECK_TIMEOUT_MS: u64 = 2000;
@@ -0,0 +1,5 @@
+//! Hằng số dùng cho JWT
+
+/// Secret HS256 dùng khi không đặt `JWT_SECRET`, chỉ dành cho development và testing.
+/// Production từ chối khởi động với secret này
+pub const DEV_JWT_SECRET: &str = "dev-secret-change-me";
@@ -1 +1,12 @@
 // Export các thành phần trong module constants
+pub mod audit;
+pub mod cache;
+pub mod events;
+pub mod filter;
+pub mod health;
+pub mod jwt;
+pub mod pagination;
+pub mod persisted_query;
+pub mod shutdown;
+pub mod transaction;
+pub mod validation;
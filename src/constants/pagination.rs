@@ -0,0 +1,7 @@
+//! Hằng số dùng cho phân trang
+
+/// Số bản ghi mặc định của một trang khi client không truyền `first`/`last`
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+
+/// Số bản ghi tối đa của một trang, giá trị lớn hơn sẽ bị giới hạn lại
+pub const MAX_PAGE_SIZE: usize = 100;
@@ -0,0 +1,14 @@
+//! Hằng số dùng cho Automatic Persisted Queries (APQ)
+
+/// Collection lưu persisted query khi dùng store `mongodb`
+pub const PERSISTED_QUERY_COLLECTION: &str = "persisted_queries";
+
+/// Phiên bản duy nhất của extension `persistedQuery` mà giao thức APQ định nghĩa
+pub const PERSISTED_QUERY_VERSION: i32 = 1;
+
+/// Thời gian (giây) giữ lại query do client lưu qua APQ trong store `mongodb`,
+/// query do admin đăng ký không hết hạn
+pub const PERSISTED_QUERY_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Độ dài tối đa (byte) của query mà client được lưu qua APQ, query dài hơn vẫn chạy nhưng không được lưu
+pub const MAX_PERSISTED_QUERY_LENGTH: usize = 16 * 1024;
@@ -0,0 +1,4 @@
+//! Hằng số dùng khi tắt server
+
+/// Thời gian tối đa (giây) chờ MongoDB client dọn dẹp cursor và session trước khi đóng ngay lập tức
+pub const MONGO_SHUTDOWN_TIMEOUT_SECONDS: u64 = 5;
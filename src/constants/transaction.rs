@@ -0,0 +1,4 @@
+//! Hằng số dùng cho transaction MongoDB
+
+/// Thời gian tối đa (giây) để thử lại một transaction gặp lỗi tạm thời, theo khuyến nghị của driver
+pub const TRANSACTION_RETRY_TIMEOUT_SECONDS: u64 = 120;
@@ -0,0 +1,15 @@
+//! Giới hạn dùng khi kiểm tra dữ liệu đầu vào
+
+/// Độ dài tối đa của tên người dùng
+pub const NAME_MAX_LENGTH: usize = 100;
+
+/// Độ dài tối đa của email (RFC 5321)
+pub const EMAIL_MAX_LENGTH: usize = 254;
+
+/// Khoảng tuổi hợp lệ
+pub const AGE_MIN: i64 = 0;
+pub const AGE_MAX: i64 = 150;
+
+/// Độ dài cho phép của mật khẩu
+pub const PASSWORD_MIN_LENGTH: usize = 8;
+pub const PASSWORD_MAX_LENGTH: usize = 128;
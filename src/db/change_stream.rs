@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use mongodb::bson::{self, doc, Bson, Document};
+use mongodb::change_stream::event::{ChangeStreamEvent, OperationType, ResumeToken};
+use mongodb::error::ErrorKind;
+use mongodb::options::{ChangeStreamOptions, FullDocumentBeforeChangeType, FullDocumentType};
+use mongodb::Database;
+use serde::de::DeserializeOwned;
+use tokio::task::JoinHandle;
+
+use crate::base::base_event::{EventBus, EventType};
+use crate::base::base_model::Status;
+use crate::constants::events::CHANGE_STREAM_RETRY_SECONDS;
+
+type WatchFn = Box<dyn Fn(Database, Arc<EventBus>, String) -> BoxFuture<'static, ()> + Send + Sync>;
+
+// Bridges MongoDB change streams into the EventBus so that writes made by
+// other instances (or directly in the database) reach local subscribers.
+// Change streams require a replica set or sharded cluster.
+pub struct ChangeStreamWatcher {
+    db: Database,
+    event_bus: Arc<EventBus>,
+    targets: HashMap<String, WatchFn>,
+}
+
+impl ChangeStreamWatcher {
+    pub fn new(db: Database, event_bus: Arc<EventBus>) -> Self {
+        Self { db, event_bus, targets: HashMap::new() }
+    }
+
+    // Declare which payload type the documents of `collection` are published as
+    pub fn register<T>(mut self, collection: &str) -> Self
+    where
+        T: DeserializeOwned + Clone + Unpin + Send + Sync + 'static,
+    {
+        self.targets.insert(
+            collection.to_string(),
+            Box::new(|db, event_bus, name| Box::pin(watch::<T>(db, event_bus, name))),
+        );
+        self
+    }
+
+    // Start one watcher task per configured collection. While a stream is
+    // open, local emitters for its payload type are switched to it as their
+    // only source so that a write is not published twice.
+    pub fn spawn(self, collections: &[String]) -> Vec<JoinHandle<()>> {
+        let mut tasks = Vec::new();
+        for name in collections {
+            let Some(watch_fn) = self.targets.get(name) else {
+                eprintln!("No change stream target registered for collection {}", name);
+                continue;
+            };
+            let task = watch_fn(self.db.clone(), self.event_bus.clone(), name.clone());
+            tasks.push(tokio::spawn(task));
+        }
+        tasks
+    }
+}
+
+// Each process starts from "now": subscribers only ever see changes made
+// after they could have subscribed, and instances never share a position.
+// The resume token is kept in memory to pick up where a dropped stream left
+// off without missing or repeating events.
+async fn watch<T>(db: Database, event_bus: Arc<EventBus>, collection: String)
+where
+    T: DeserializeOwned + Clone + Unpin + Send + Sync + 'static,
+{
+    enable_pre_images(&db, &collection).await;
+
+    let mut resume_token = None;
+    loop {
+        match watch_once::<T>(&db, &event_bus, &collection, &mut resume_token).await {
+            Ok(()) => eprintln!("Change stream on {} closed, reopening", collection),
+            Err(e) => {
+                eprintln!("Change stream on {} failed: {}", collection, e);
+                if is_history_lost(&e) {
+                    eprintln!("Resume point on {} is gone, restarting from now", collection);
+                    resume_token = None;
+                }
+            }
+        }
+        // A resumed stream replays the writes made while it was down, so local
+        // emitters stay off. Without a token it can't, so they take over until
+        // the stream is open again.
+        if resume_token.is_none() {
+            event_bus.emitter::<T>().set_external_source(false);
+        }
+        tokio::time::sleep(Duration::from_secs(CHANGE_STREAM_RETRY_SECONDS)).await;
+    }
+}
+
+async fn watch_once<T>(
+    db: &Database,
+    event_bus: &EventBus,
+    collection: &str,
+    resume_token: &mut Option<ResumeToken>,
+) -> mongodb::error::Result<()>
+where
+    T: DeserializeOwned + Clone + Unpin + Send + Sync + 'static,
+{
+    let options = ChangeStreamOptions::builder()
+        .full_document(Some(FullDocumentType::UpdateLookup))
+        .full_document_before_change(Some(FullDocumentBeforeChangeType::WhenAvailable))
+        .resume_after(resume_token.clone())
+        .build();
+    let pipeline = [doc! {
+        "$match": { "operationType": { "$in": ["insert", "update", "replace", "delete"] } }
+    }];
+
+    // Raw documents, so one that doesn't decode as T is skipped instead of
+    // failing the stream on the same event after every reconnect
+    let mut stream = db.collection::<Document>(collection).watch(pipeline, options).await?;
+    event_bus.emitter::<T>().set_external_source(true);
+    println!("Watching change stream on {}", collection);
+
+    // Remember the opening position too, so writes made before the first
+    // event are replayed if the stream drops
+    *resume_token = stream.resume_token().or(resume_token.take());
+    while let Some(event) = stream.next().await {
+        let event = event?;
+        publish::<T>(event_bus, collection, event);
+        *resume_token = stream.resume_token().or(resume_token.take());
+    }
+
+    Ok(())
+}
+
+fn publish<T>(event_bus: &EventBus, collection: &str, event: ChangeStreamEvent<Document>)
+where
+    T: DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    let (event_type, document) = match event.operation_type {
+        OperationType::Insert => (EventType::Created, event.full_document),
+        // A soft delete is an update of `status` but reaches subscribers as a delete
+        OperationType::Update if is_soft_delete(&event) => (EventType::Deleted, event.full_document),
+        OperationType::Update | OperationType::Replace => (EventType::Updated, event.full_document),
+        OperationType::Delete => (EventType::Deleted, event.full_document_before_change),
+        _ => return,
+    };
+
+    // Deletes only carry the document when pre-images are enabled, and
+    // updates lose it if the document was removed before the lookup
+    let Some(document) = document else {
+        eprintln!(
+            "Skipping {:?} change on {} without a document: {:?}",
+            event_type, collection, event.document_key
+        );
+        return;
+    };
+
+    match bson::from_document::<T>(document) {
+        Ok(payload) => event_bus.publish(event_type, payload),
+        Err(e) => eprintln!(
+            "Skipping {:?} change on {} that does not match the model ({}): {:?}",
+            event_type, collection, e, event.document_key
+        ),
+    }
+}
+
+fn is_soft_delete<T>(event: &ChangeStreamEvent<T>) -> bool {
+    event
+        .update_description
+        .as_ref()
+        .and_then(|description| description.updated_fields.get("status"))
+        .is_some_and(|status| *status == Bson::Int32(Status::Deleted.code()))
+}
+
+// The oplog no longer holds the resume point (ChangeStreamHistoryLost) or the
+// stream can't be resumed at all (ChangeStreamFatalError)
+fn is_history_lost(err: &mongodb::error::Error) -> bool {
+    matches!(*err.kind, ErrorKind::Command(ref e) if e.code == 286 || e.code == 280)
+}
+
+// Pre-images let delete events carry the removed document (MongoDB 6.0+).
+// Failure is not fatal: deletes are then skipped with a log line.
+async fn enable_pre_images(db: &Database, collection: &str) {
+    let command = doc! {
+        "collMod": collection,
+        "changeStreamPreAndPostImages": { "enabled": true },
+    };
+    if let Err(e) = db.run_command(command, None).await {
+        eprintln!("Could not enable pre-images on {}: {}", collection, e);
+    }
+}
@@ -1,12 +1,10 @@
-use mongodb::{Client, Database, options::ClientOptions};
+use mongodb::{bson::doc, Client, Database, options::ClientOptions};
 use crate::configs::get_config;
 
-pub async fn connect_db() -> Database {
-    // Lấy cấu hình từ singleton config
-    let config = get_config();
-    
+// Tạo client MongoDB; giữ lại client để mở session cho transaction
+pub async fn connect_client() -> Client {
     // Lấy MongoDB URI từ cấu hình
-    let mongo_uri = config.mongo_uri();
+    let mongo_uri = get_config().mongo_uri();
     
     // Tạo client options từ connection string
     let client_options = ClientOptions::parse(mongo_uri).await
@@ -16,12 +14,14 @@ pub async fn connect_db() -> Database {
     let client = Client::with_options(client_options)
         .expect("Không thể kết nối đến MongoDB");
     
-    // Lấy database từ cấu hình
-    let db_name = config.mongo_db();
-    let db = client.database(db_name);
-    
     println!("Đã kết nối thành công đến MongoDB!");
-    db
+    client
+}
+
+pub async fn connect_db() -> Database {
+    // Lấy database từ cấu hình
+    let client = connect_client().await;
+    client.database(get_config().mongo_db())
 }
 
 // Hàm kiểm tra kết nối
@@ -37,3 +37,9 @@ pub async fn test_connection(db: &Database) -> bool {
         }
     }
 }
+
+// Lệnh ping nhẹ nhất của MongoDB, dùng cho readiness check
+pub async fn ping(db: &Database) -> mongodb::error::Result<()> {
+    db.run_command(doc! { "ping": 1 }, None).await?;
+    Ok(())
+}
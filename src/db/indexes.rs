@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use mongodb::bson::{Bson, Document};
+use mongodb::options::IndexOptions;
+use mongodb::{Database, IndexModel};
+
+use crate::base::error::ServiceResult;
+
+// One index of a collection. Compound indexes are simply several keys in
+// `keys`; the option helpers can be chained, e.g. a partial unique index.
+#[derive(Clone, Debug)]
+pub struct IndexSpec {
+    keys: Document,
+    options: IndexOptions,
+}
+
+impl IndexSpec {
+    pub fn new(keys: Document) -> Self {
+        Self { keys, options: IndexOptions::default() }
+    }
+
+    // Full text index over the given fields
+    pub fn text(fields: &[&str]) -> Self {
+        let keys = fields
+            .iter()
+            .map(|field| (field.to_string(), Bson::from("text")))
+            .collect();
+        Self::new(keys)
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.options.name = Some(name.to_string());
+        self
+    }
+
+    pub fn unique(mut self) -> Self {
+        self.options.unique = Some(true);
+        self
+    }
+
+    // Documents expire `after` the date stored in the (single) indexed field
+    pub fn ttl(mut self, after: Duration) -> Self {
+        self.options.expire_after = Some(after);
+        self
+    }
+
+    // Only index documents matching `filter`
+    pub fn partial(mut self, filter: Document) -> Self {
+        self.options.partial_filter_expression = Some(filter);
+        self
+    }
+
+    fn into_model(self) -> IndexModel {
+        IndexModel::builder().keys(self.keys).options(self.options).build()
+    }
+}
+
+// Implemented by models that declare the indexes of their collection
+pub trait ModelIndexes {
+    fn indexes() -> Vec<IndexSpec>;
+}
+
+// Collects the index declarations of every model so they can be created
+// together at startup. Creating an index that already exists with the same
+// options is a no-op in MongoDB, so `ensure` is safe to run on every boot.
+#[derive(Default)]
+pub struct IndexRegistry {
+    collections: Vec<(String, Vec<IndexSpec>)>,
+}
+
+impl IndexRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<T: ModelIndexes>(mut self, collection: &str) -> Self {
+        self.collections.push((collection.to_string(), T::indexes()));
+        self
+    }
+
+    pub async fn ensure(&self, db: &Database) -> ServiceResult<()> {
+        for (collection, specs) in &self.collections {
+            if specs.is_empty() {
+                continue;
+            }
+            let models = specs.iter().cloned().map(IndexSpec::into_model).collect::<Vec<_>>();
+            let result = db
+                .collection::<Document>(collection)
+                .create_indexes(models, None)
+                .await?;
+            println!("Đã đảm bảo index cho {}: {}", collection, result.index_names.join(", "));
+        }
+        Ok(())
+    }
+}
@@ -1,2 +1,5 @@
 // Export thành phần db
+pub mod change_stream;
 pub mod connection;
+pub mod indexes;
+pub mod transaction;
@@ -0,0 +1,270 @@
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextExecute, NextPrepareRequest,
+};
+use async_graphql::{Pos, Request, Response, ServerResult, Value};
+use futures::future::BoxFuture;
+use mongodb::error::{TRANSIENT_TRANSACTION_ERROR, UNKNOWN_TRANSACTION_COMMIT_RESULT};
+use mongodb::{Client, ClientSession};
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+
+use crate::base::error::{ServiceError, ServiceResult};
+use crate::constants::transaction::TRANSACTION_RETRY_TIMEOUT_SECONDS;
+
+// Run `body` in a transaction and commit it. The whole body is run again when
+// the server reports a transient transaction error, and the commit alone is
+// retried when its outcome is unknown, until the retry timeout runs out.
+// `context` is handed back to the body so it can borrow services, e.g.
+//
+//     with_transaction(&client, &users, |session, users| async move {
+//         users.create_with_session(user, session).await
+//     }.boxed()).await
+pub async fn with_transaction<C, R, F>(client: &Client, context: &C, mut body: F) -> ServiceResult<R>
+where
+    C: Sync + ?Sized,
+    F: for<'s> FnMut(&'s mut ClientSession, &'s C) -> BoxFuture<'s, ServiceResult<R>>,
+{
+    let deadline = Instant::now() + Duration::from_secs(TRANSACTION_RETRY_TIMEOUT_SECONDS);
+    let mut session = client.start_session(None).await?;
+
+    'attempt: loop {
+        session.start_transaction(None).await?;
+
+        let result = match body(&mut session, context).await {
+            Ok(result) => result,
+            Err(err) => {
+                // The server may already have aborted the transaction
+                let _ = session.abort_transaction().await;
+                if matches!(err, ServiceError::TransientTransaction(_)) && Instant::now() < deadline {
+                    continue 'attempt;
+                }
+                return Err(err);
+            }
+        };
+
+        loop {
+            match session.commit_transaction().await {
+                Ok(()) => return Ok(result),
+                Err(err) if err.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT) && Instant::now() < deadline => {
+                    continue;
+                }
+                Err(err) if err.contains_label(TRANSIENT_TRANSACTION_ERROR) && Instant::now() < deadline => {
+                    continue 'attempt;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+// One transaction shared by every service taking part in a request. The
+// session is only started by the first database call, so requests that never
+// touch the database cost nothing. Side effects that must not be seen before
+// the data is durable (events) are queued with `after_commit`.
+pub struct UnitOfWork {
+    client: Client,
+    session: Mutex<Option<ClientSession>>,
+    after_commit: StdMutex<Vec<Callback>>,
+}
+
+impl UnitOfWork {
+    pub fn new(client: Client) -> Self {
+        Self { client, session: Mutex::new(None), after_commit: StdMutex::new(Vec::new()) }
+    }
+
+    // Session of the running transaction, started on first use. Concurrent
+    // callers wait for each other since a session can't be shared.
+    pub async fn session(&self) -> ServiceResult<MappedMutexGuard<'_, ClientSession>> {
+        let mut guard = self.session.lock().await;
+        if guard.is_none() {
+            let mut session = self.client.start_session(None).await?;
+            session.start_transaction(None).await?;
+            *guard = Some(session);
+        }
+        MutexGuard::try_map(guard, Option::as_mut)
+            .map_err(|_| ServiceError::InternalServerError("Transaction session missing".to_string()))
+    }
+
+    pub fn after_commit(&self, callback: impl FnOnce() + Send + 'static) {
+        if let Ok(mut callbacks) = self.after_commit.lock() {
+            callbacks.push(Box::new(callback));
+        }
+    }
+
+    // Commit if a transaction was started, then run the queued callbacks
+    pub async fn commit(&self) -> ServiceResult<()> {
+        let deadline = Instant::now() + Duration::from_secs(TRANSACTION_RETRY_TIMEOUT_SECONDS);
+        if let Some(mut session) = self.session.lock().await.take() {
+            loop {
+                match session.commit_transaction().await {
+                    Ok(()) => break,
+                    Err(err) if err.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT) && Instant::now() < deadline => {
+                        continue;
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        }
+
+        let callbacks = self.after_commit.lock().map(|mut c| std::mem::take(&mut *c)).unwrap_or_default();
+        for callback in callbacks {
+            callback();
+        }
+        Ok(())
+    }
+
+    // Abort the transaction and drop the queued callbacks. Returns whether
+    // there was a transaction to roll back.
+    pub async fn rollback(&self) -> bool {
+        if let Ok(mut callbacks) = self.after_commit.lock() {
+            callbacks.clear();
+        }
+        match self.session.lock().await.take() {
+            Some(mut session) => {
+                let _ = session.abort_transaction().await;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// Schema extension giving every request its own UnitOfWork. The request
+// commits unless a root field (the mutation resolvers themselves) failed, so
+// one mutation touching several collections is atomic. Errors in nested
+// fields, such as a guard hiding `email` in the returned user, come from
+// reading the result back and leave the write in place. Resolvers hand the unit of
+// work to their services, see UserService::with_unit_of_work.
+pub struct UnitOfWorkExtension {
+    client: Client,
+}
+
+impl UnitOfWorkExtension {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+impl ExtensionFactory for UnitOfWorkExtension {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(UnitOfWorkRequest { client: self.client.clone() })
+    }
+}
+
+struct UnitOfWorkRequest {
+    client: Client,
+}
+
+#[async_trait::async_trait]
+impl Extension for UnitOfWorkRequest {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        let request = request.data(Arc::new(UnitOfWork::new(self.client.clone())));
+        next.run(ctx, request).await
+    }
+
+    async fn execute(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        operation_name: Option<&str>,
+        next: NextExecute<'_>,
+    ) -> Response {
+        let mut response = next.run(ctx, operation_name).await;
+        let Some(uow) = ctx.data_opt::<Arc<UnitOfWork>>() else {
+            return response;
+        };
+
+        if !root_field_failed(&response) {
+            if let Err(err) = uow.commit().await {
+                let error = async_graphql::Error::from(err).into_server_error(Pos::default());
+                return Response::from_errors(vec![error]);
+            }
+        } else if uow.rollback().await {
+            // Nothing the request wrote was kept, don't report partial results
+            response.data = Value::Null;
+        }
+        response
+    }
+}
+
+// Whether an error belongs to a root field or to the request as a whole.
+// Errors deeper in the selection come from reading the result back, after
+// the resolver's writes succeeded.
+fn root_field_failed(response: &Response) -> bool {
+    response.errors.iter().any(|error| error.path.len() <= 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use async_graphql::{Context, EmptySubscription, Object, Schema, SimpleObject};
+
+    use super::*;
+
+    static COMMITTED: AtomicBool = AtomicBool::new(false);
+
+    #[derive(SimpleObject)]
+    #[graphql(complex)]
+    struct Created {
+        id: i32,
+    }
+
+    #[async_graphql::ComplexObject]
+    impl Created {
+        // Stands in for a guarded field such as User.email
+        async fn hidden(&self) -> async_graphql::Result<Option<String>> {
+            Err("Forbidden".into())
+        }
+    }
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+        async fn ok(&self) -> bool {
+            true
+        }
+    }
+
+    struct Mutation;
+
+    #[Object]
+    impl Mutation {
+        async fn create(&self, ctx: &Context<'_>, fail: bool) -> async_graphql::Result<Created> {
+            ctx.data::<Arc<UnitOfWork>>()?.after_commit(|| COMMITTED.store(true, Ordering::SeqCst));
+            if fail {
+                return Err("Write failed".into());
+            }
+            Ok(Created { id: 1 })
+        }
+    }
+
+    // Both cases share COMMITTED so they run in one test
+    #[tokio::test]
+    async fn commits_unless_a_root_field_fails() {
+        // No session is ever started, so the client never connects
+        let client = Client::with_uri_str("mongodb://localhost:27017").await.unwrap();
+        let schema = Schema::build(Query, Mutation, EmptySubscription)
+            .extension(UnitOfWorkExtension::new(client))
+            .finish();
+
+        // A moderator creating a user: the write succeeds, the returned
+        // user's email is hidden by its guard
+        let response = schema.execute("mutation { create(fail: false) { id hidden } }").await;
+        assert_eq!(response.errors[0].path.len(), 2);
+        assert!(COMMITTED.swap(false, Ordering::SeqCst));
+
+        let response = schema.execute("mutation { create(fail: true) { id } }").await;
+        assert_eq!(response.errors[0].path.len(), 1);
+        assert!(!COMMITTED.load(Ordering::SeqCst));
+    }
+}
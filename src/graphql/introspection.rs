@@ -0,0 +1,74 @@
+use std::any::TypeId;
+use std::sync::Arc;
+
+use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest};
+use async_graphql::{Request, ServerResult};
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+
+use crate::base::auth::{request_user, Role};
+use crate::configs::get_config;
+
+// Marker put in the request data when the request carried the configured
+// `x-introspection-secret` header
+#[derive(Debug, Clone, Copy)]
+pub struct IntrospectionSecret;
+
+// Axum extractor checking the `x-introspection-secret` header against the
+// configured secret. Never matches when no secret is configured.
+pub struct IntrospectionHeader(pub Option<IntrospectionSecret>);
+
+impl<S> FromRequestParts<S> for IntrospectionHeader
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header = parts
+            .headers
+            .get("x-introspection-secret")
+            .and_then(|value| value.to_str().ok());
+
+        let matches = match (header, get_config().introspection_secret()) {
+            (Some(header), Some(secret)) => constant_time_eq(header.as_bytes(), secret.as_bytes()),
+            _ => false,
+        };
+        Ok(Self(matches.then_some(IntrospectionSecret)))
+    }
+}
+
+// Compare without returning early so the secret can't be guessed by timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Registered when introspection is turned off in the config. Requests have
+// introspection disabled unless they come from an admin or carry the shared
+// secret. Checked per request so WebSocket operations are covered too, the
+// caller there comes from the connection init data.
+pub struct IntrospectionPolicy;
+
+impl ExtensionFactory for IntrospectionPolicy {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(IntrospectionPolicyRequest)
+    }
+}
+
+struct IntrospectionPolicyRequest;
+
+#[async_trait::async_trait]
+impl Extension for IntrospectionPolicyRequest {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        let has_secret = request.data.contains_key(&TypeId::of::<IntrospectionSecret>());
+        let is_admin = request_user(ctx, &request).is_some_and(|user| user.has_role(Role::Admin));
+
+        let request = if is_admin || has_secret { request } else { request.disable_introspection() };
+        next.run(ctx, request).await
+    }
+}
@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextParseQuery, NextValidation,
+};
+use async_graphql::parser::types::{ExecutableDocument, Selection, SelectionSet};
+use async_graphql::{ErrorExtensionValues, ServerError, ServerResult, ValidationResult, Variables};
+
+use crate::configs::config::AppConfig;
+
+// Rejects queries over the configured depth, complexity, alias and recursion
+// limits with a QUERY_LIMIT_EXCEEDED error naming the limit. Depth and
+// complexity are computed by async-graphql's validation, see
+// `#[graphql(complexity)]` on list fields for how lists are weighted.
+#[derive(Clone, Copy)]
+pub struct QueryLimits {
+    max_depth: usize,
+    max_complexity: usize,
+    max_aliases: usize,
+    max_recursion: usize,
+}
+
+impl QueryLimits {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            max_depth: config.max_depth(),
+            max_complexity: config.max_complexity(),
+            max_aliases: config.max_aliases(),
+            max_recursion: config.max_recursion(),
+        }
+    }
+
+    // The recursion limit is enforced by the parser itself, whose error is
+    // recognized by its message in parse_query (pinned by the tests below)
+    pub fn max_recursion(&self) -> usize {
+        self.max_recursion
+    }
+}
+
+impl ExtensionFactory for QueryLimits {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(*self)
+    }
+}
+
+#[async_trait::async_trait]
+impl Extension for QueryLimits {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await.map_err(|err| {
+            if err.message.starts_with("The recursion depth of the query") {
+                limit_exceeded("recursion", self.max_recursion, None)
+            } else {
+                err
+            }
+        })?;
+
+        let aliases = count_aliases(&document);
+        if aliases > self.max_aliases {
+            return Err(limit_exceeded("aliases", self.max_aliases, Some(aliases)));
+        }
+        Ok(document)
+    }
+
+    async fn validation(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextValidation<'_>,
+    ) -> Result<ValidationResult, Vec<ServerError>> {
+        let result = next.run(ctx).await?;
+        if result.depth > self.max_depth {
+            return Err(vec![limit_exceeded("depth", self.max_depth, Some(result.depth))]);
+        }
+        if result.complexity > self.max_complexity {
+            return Err(vec![limit_exceeded("complexity", self.max_complexity, Some(result.complexity))]);
+        }
+        Ok(result)
+    }
+}
+
+fn limit_exceeded(limit: &str, max: usize, actual: Option<usize>) -> ServerError {
+    let message = match actual {
+        Some(actual) => format!("Query exceeds the {} limit of {} (got {})", limit, max, actual),
+        None => format!("Query exceeds the {} limit of {}", limit, max),
+    };
+
+    let mut extensions = ErrorExtensionValues::default();
+    extensions.set("code", "QUERY_LIMIT_EXCEEDED");
+    extensions.set("limit", limit);
+    extensions.set("max", max as u64);
+    if let Some(actual) = actual {
+        extensions.set("actual", actual as u64);
+    }
+
+    let mut error = ServerError::new(message, None);
+    error.extensions = Some(extensions);
+    error
+}
+
+// Aliases written in the document, fragments counted once
+fn count_aliases(document: &ExecutableDocument) -> usize {
+    let operations = document
+        .operations
+        .iter()
+        .map(|(_, operation)| count_in_selection(&operation.node.selection_set.node));
+    let fragments = document
+        .fragments
+        .values()
+        .map(|fragment| count_in_selection(&fragment.node.selection_set.node));
+    operations.chain(fragments).sum()
+}
+
+fn count_in_selection(selection_set: &SelectionSet) -> usize {
+    selection_set
+        .items
+        .iter()
+        .map(|selection| match &selection.node {
+            Selection::Field(field) => {
+                usize::from(field.node.alias.is_some()) + count_in_selection(&field.node.selection_set.node)
+            }
+            Selection::InlineFragment(fragment) => count_in_selection(&fragment.node.selection_set.node),
+            Selection::FragmentSpread(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{EmptyMutation, EmptySubscription, Object, Schema, Value};
+
+    use super::*;
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+        async fn ok(&self) -> bool {
+            true
+        }
+    }
+
+    fn schema() -> Schema<Query, EmptyMutation, EmptySubscription> {
+        let limits = QueryLimits { max_depth: 100, max_complexity: 1000, max_aliases: 3, max_recursion: 5 };
+        Schema::build(Query, EmptyMutation, EmptySubscription)
+            .limit_recursive_depth(limits.max_recursion())
+            .extension(limits)
+            .finish()
+    }
+
+    fn limit_of(response: &async_graphql::Response) -> Option<Value> {
+        let extensions = response.errors.first()?.extensions.as_ref()?;
+        assert_eq!(extensions.get("code"), Some(&Value::from("QUERY_LIMIT_EXCEEDED")));
+        extensions.get("limit").cloned()
+    }
+
+    // Pins the parser's error message the recursion limit is recognized by
+    #[tokio::test]
+    async fn parser_recursion_error_is_reported_as_a_limit() {
+        let query = format!("{}ok{}", "{ a ".repeat(10), " }".repeat(10));
+        let response = schema().execute(query).await;
+        assert_eq!(limit_of(&response), Some(Value::from("recursion")));
+    }
+
+    #[tokio::test]
+    async fn aliases_over_the_limit_are_rejected() {
+        let response = schema().execute("{ a: ok b: ok c: ok d: ok }").await;
+        assert_eq!(limit_of(&response), Some(Value::from("aliases")));
+
+        let response = schema().execute("{ a: ok b: ok c: ok }").await;
+        assert!(response.errors.is_empty());
+    }
+}
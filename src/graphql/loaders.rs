@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest};
+use async_graphql::{Request, ServerResult};
+use mongodb::Database;
+
+use crate::base::auth::CurrentGrants;
+use crate::base::loader::{model_loader, ModelLoader};
+use crate::graphql::modules::user::model::User;
+use crate::graphql::modules::user::service::UserService;
+
+pub type UserLoader = ModelLoader<UserService, User>;
+
+// Gives every request fresh loaders so batching and caching never cross
+// requests. Resolvers read them with `ctx.data::<UserLoader>()`. Guards read
+// the caller's current grants the same way, see base::auth::CurrentGrants.
+pub struct LoadersExtension {
+    db: Database,
+}
+
+impl LoadersExtension {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+}
+
+impl ExtensionFactory for LoadersExtension {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(RequestLoaders { db: self.db.clone() })
+    }
+}
+
+struct RequestLoaders {
+    db: Database,
+}
+
+#[async_trait::async_trait]
+impl Extension for RequestLoaders {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        let request = request
+            .data::<UserLoader>(model_loader(UserService::new(&self.db)))
+            .data(CurrentGrants::new(UserService::new(&self.db)));
+        next.run(ctx, request).await
+    }
+}
@@ -1,3 +1,8 @@
 // Export các thành phần trong module graphql
+pub mod introspection;
+pub mod limits;
+pub mod loaders;
 pub mod modules;
+pub mod persisted_queries;
+pub mod response_cache;
 pub mod schema;
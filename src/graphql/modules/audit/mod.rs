@@ -0,0 +1,4 @@
+// Export các thành phần trong module audit
+pub mod model;
+pub mod service;
+pub mod resolver;
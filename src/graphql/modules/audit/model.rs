@@ -0,0 +1,34 @@
+use async_graphql::InputObject;
+use mongodb::bson::{to_bson, Document};
+
+use crate::base::audit::AuditOperation;
+use crate::base::error::ServiceResult;
+use crate::base::filter::{DateTimeFilter, FieldFilter, StringFilter};
+
+#[derive(InputObject, Default)]
+pub struct AuditOperationFilter {
+    pub eq: Option<AuditOperation>,
+    #[graphql(name = "in")]
+    pub in_: Option<Vec<AuditOperation>>,
+}
+
+impl FieldFilter for AuditOperationFilter {
+    fn to_condition(&self) -> ServiceResult<Document> {
+        let mut condition = Document::new();
+        if let Some(eq) = &self.eq {
+            condition.insert("$eq", to_bson(eq)?);
+        }
+        if let Some(in_) = &self.in_ {
+            condition.insert("$in", to_bson(in_)?);
+        }
+        Ok(condition)
+    }
+}
+
+crate::model_filter!(AuditLogFilterInput {
+    actor_id: StringFilter => "actor_id",
+    operation: AuditOperationFilter => "operation",
+    collection: StringFilter => "collection",
+    request_id: StringFilter => "request_id",
+    timestamp: DateTimeFilter => "timestamp",
+});
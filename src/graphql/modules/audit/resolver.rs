@@ -0,0 +1,47 @@
+use async_graphql::{connection, Context, Object, ID};
+
+use crate::base::audit::AuditLog;
+use crate::base::auth::Role;
+use crate::base::filter::ModelFilter;
+use crate::base::guard::RoleGuard;
+use crate::base::pagination::{list_complexity, page_size, ModelConnection, PageArgs};
+use crate::constants::pagination::DEFAULT_PAGE_SIZE;
+use crate::graphql::modules::audit::model::AuditLogFilterInput;
+use crate::graphql::modules::audit::service::AuditService;
+use crate::graphql::schema::DbContext;
+
+#[derive(Default)]
+pub struct AuditQuery;
+
+#[Object]
+impl AuditQuery {
+    // Who changed what and when, oldest first; use `last` for the latest entries
+    #[graphql(
+        guard = "RoleGuard::new(Role::Admin)",
+        complexity = "list_complexity(page_size(first.or(last), DEFAULT_PAGE_SIZE), child_complexity)",
+        cache_control(no_cache)
+    )]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn audit_log(
+        &self,
+        ctx: &Context<'_>,
+        entity_id: Option<ID>,
+        filter: Option<AuditLogFilterInput>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<ModelConnection<AuditLog>, async_graphql::Error> {
+        let filter = filter.unwrap_or_default().to_document()?;
+        let db_ctx = ctx.data::<DbContext>()?;
+        let audit_service = AuditService::new(&db_ctx.db);
+
+        let count_total = ctx.look_ahead().field("totalCount").exists();
+        connection::query(after, before, first, last, |after, before, first, last| async move {
+            let args = PageArgs::new(after, before, first, last)?.with_total_count(count_total);
+            let page = audit_service.history(entity_id.as_ref(), filter, args).await?;
+            Ok::<_, async_graphql::Error>(page.into_connection())
+        })
+        .await
+    }
+}
@@ -0,0 +1,44 @@
+use async_graphql::ID;
+use async_trait::async_trait;
+use mongodb::bson::{doc, Document};
+use mongodb::{Collection, Database};
+
+use crate::base::audit::AuditLog;
+use crate::base::base_model::ModelId;
+use crate::base::base_service::BaseService;
+use crate::base::error::ServiceResult;
+use crate::base::filter::and_clauses;
+use crate::base::pagination::{Page, PageArgs};
+use crate::constants::audit::AUDIT_COLLECTION;
+
+// Read side of the audit log; entries are written by base::audit::AuditTrail
+pub struct AuditService {
+    collection: Collection<AuditLog>,
+}
+
+#[async_trait]
+impl BaseService<AuditLog> for AuditService {
+    fn get_collection(&self) -> Collection<AuditLog> {
+        self.collection.clone()
+    }
+    
+    // Audit entries have no lifecycle status
+    fn include_deleted(&self) -> bool {
+        true
+    }
+}
+
+impl AuditService {
+    pub fn new(db: &Database) -> Self {
+        Self { collection: db.collection(AUDIT_COLLECTION) }
+    }
+    
+    // History of one record, or of every record when `entity_id` is None
+    pub async fn history(&self, entity_id: Option<&ID>, filter: Document, args: PageArgs) -> ServiceResult<Page<AuditLog>> {
+        let mut clauses = vec![filter];
+        if let Some(entity_id) = entity_id {
+            clauses.push(doc! { "entity_id": entity_id.to_object_id()? });
+        }
+        self.find_page(and_clauses(clauses), args).await
+    }
+}
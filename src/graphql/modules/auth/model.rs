@@ -0,0 +1,62 @@
+use async_graphql::{InputObject, SimpleObject};
+use std::time::Duration;
+
+use mongodb::bson::{doc, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::base::validation::{Validate, Validation};
+use crate::constants::validation::{
+    AGE_MAX, AGE_MIN, EMAIL_MAX_LENGTH, NAME_MAX_LENGTH, PASSWORD_MAX_LENGTH, PASSWORD_MIN_LENGTH,
+};
+use crate::db::indexes::{IndexSpec, ModelIndexes};
+use crate::graphql::modules::user::model::User;
+
+// Kết quả của register, login và refreshToken
+#[derive(SimpleObject)]
+pub struct AuthPayload {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+    // Số giây trước khi access token hết hạn
+    pub expires_in: i64,
+    pub user: User,
+}
+
+// Input cho mutation register
+#[derive(InputObject)]
+pub struct RegisterInput {
+    pub name: String,
+    pub email: String,
+    #[graphql(secret)]
+    pub password: String,
+    pub age: Option<i32>,
+}
+
+impl Validate for RegisterInput {
+    fn validate(&self, v: &mut Validation) {
+        v.string("name", &self.name).not_blank().max_length(NAME_MAX_LENGTH);
+        v.string("email", &self.email).not_blank().max_length(EMAIL_MAX_LENGTH).email();
+        v.string("password", &self.password).min_length(PASSWORD_MIN_LENGTH).max_length(PASSWORD_MAX_LENGTH);
+        v.int("age", &self.age).range(AGE_MIN, AGE_MAX);
+    }
+}
+
+// Refresh token đã phát hành, lưu theo jti để có thể thu hồi khi logout hoặc khi xoay vòng
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RefreshTokenRecord {
+    #[serde(rename = "_id")]
+    pub jti: String,
+    pub user_id: String,
+    pub expires_at: DateTime,
+    pub revoked: bool,
+}
+
+// MongoDB tự xoá token khi đã hết hạn
+impl ModelIndexes for RefreshTokenRecord {
+    fn indexes() -> Vec<IndexSpec> {
+        vec![
+            IndexSpec::new(doc! { "expires_at": 1 }).name("expires_at_ttl").ttl(Duration::ZERO),
+            IndexSpec::new(doc! { "user_id": 1, "revoked": 1 }).name("user_id_revoked"),
+        ]
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use async_graphql::{Context, Object, Result};
+
+use crate::base::audit::AuditTrail;
+use crate::base::auth::CurrentUser;
+use crate::base::base_event::EventBus;
+use crate::base::base_model::ModelId;
+use crate::base::error::ServiceError;
+use crate::db::transaction::UnitOfWork;
+use crate::graphql::loaders::UserLoader;
+use crate::graphql::modules::auth::model::{AuthPayload, RegisterInput};
+use crate::graphql::modules::auth::service::AuthService;
+use crate::graphql::modules::user::model::User;
+use crate::graphql::schema::DbContext;
+use crate::helpers::jwt::JwtService;
+
+#[derive(Default)]
+pub struct AuthQuery;
+
+#[Object]
+impl AuthQuery {
+    // The authenticated user, from the bearer token of the request
+    #[graphql(cache_control(max_age = 60, private))]
+    pub async fn me(&self, ctx: &Context<'_>) -> Result<User, async_graphql::Error> {
+        let current_user = ctx.data_opt::<CurrentUser>()
+            .ok_or_else(|| ServiceError::AuthenticationError("Not authenticated".to_string()))?;
+
+        let loader = ctx.data::<UserLoader>()?;
+        let user = loader.load_one(current_user.id.to_object_id()?).await?
+            .ok_or_else(|| ServiceError::AuthenticationError("User no longer exists".to_string()))?;
+        Ok(user)
+    }
+}
+
+#[derive(Default)]
+pub struct AuthMutation;
+
+#[Object]
+impl AuthMutation {
+    pub async fn register(
+        &self,
+        ctx: &Context<'_>,
+        input: RegisterInput,
+    ) -> Result<AuthPayload, async_graphql::Error> {
+        let db_ctx = ctx.data::<DbContext>()?;
+        let jwt = ctx.data::<Arc<JwtService>>()?;
+        let event_bus = ctx.data::<Arc<EventBus>>()?;
+        let auth_service = AuthService::new(&db_ctx.db, jwt)
+            .with_events(event_bus.emitter::<User>())
+            .with_audit(AuditTrail::from_context(&db_ctx.db, ctx))
+            .with_unit_of_work(ctx.data_opt::<Arc<UnitOfWork>>().cloned());
+        Ok(auth_service.register(input).await?)
+    }
+
+    pub async fn login(
+        &self,
+        ctx: &Context<'_>,
+        email: String,
+        #[graphql(secret)] password: String,
+    ) -> Result<AuthPayload, async_graphql::Error> {
+        let db_ctx = ctx.data::<DbContext>()?;
+        let jwt = ctx.data::<Arc<JwtService>>()?;
+        let auth_service = AuthService::new(&db_ctx.db, jwt).with_unit_of_work(ctx.data_opt::<Arc<UnitOfWork>>().cloned());
+        Ok(auth_service.login(&email, password).await?)
+    }
+
+    pub async fn refresh_token(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(secret)] refresh_token: String,
+    ) -> Result<AuthPayload, async_graphql::Error> {
+        let db_ctx = ctx.data::<DbContext>()?;
+        let jwt = ctx.data::<Arc<JwtService>>()?;
+        let auth_service = AuthService::new(&db_ctx.db, jwt).with_unit_of_work(ctx.data_opt::<Arc<UnitOfWork>>().cloned());
+        Ok(auth_service.refresh(&refresh_token).await?)
+    }
+
+    // Revoke a refresh token; returns false if it was already revoked
+    pub async fn logout(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(secret)] refresh_token: String,
+    ) -> Result<bool, async_graphql::Error> {
+        let db_ctx = ctx.data::<DbContext>()?;
+        let jwt = ctx.data::<Arc<JwtService>>()?;
+        let auth_service = AuthService::new(&db_ctx.db, jwt).with_unit_of_work(ctx.data_opt::<Arc<UnitOfWork>>().cloned());
+        Ok(auth_service.logout(&refresh_token).await?)
+    }
+}
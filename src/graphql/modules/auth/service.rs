@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use mongodb::bson::{doc, DateTime};
+use mongodb::{Collection, Database};
+
+use crate::base::audit::AuditTrail;
+use crate::base::base_event::EventEmitter;
+use crate::base::base_service::BaseService;
+use crate::base::error::{ServiceError, ServiceResult};
+use crate::base::validation::Validation;
+use crate::db::transaction::UnitOfWork;
+use crate::graphql::modules::auth::model::{AuthPayload, RefreshTokenRecord, RegisterInput};
+use crate::graphql::modules::user::model::User;
+use crate::graphql::modules::user::service::UserService;
+use crate::helpers::jwt::{JwtService, TokenType};
+use crate::helpers::password::{dummy_password_hash, hash_password, verify_password};
+
+pub struct AuthService<'a> {
+    users: UserService,
+    refresh_tokens: Collection<RefreshTokenRecord>,
+    jwt: &'a JwtService,
+    unit_of_work: Option<Arc<UnitOfWork>>,
+}
+
+impl<'a> AuthService<'a> {
+    pub fn new(db: &Database, jwt: &'a JwtService) -> Self {
+        Self {
+            users: UserService::new(db),
+            refresh_tokens: db.collection("refresh_tokens"),
+            jwt,
+            unit_of_work: None,
+        }
+    }
+    
+    pub fn with_events(mut self, events: EventEmitter<User>) -> Self {
+        self.users = self.users.with_events(events);
+        self
+    }
+    
+    // Record user writes in the audit log; refresh tokens are not audited
+    pub fn with_audit(mut self, audit: AuditTrail) -> Self {
+        self.users = self.users.with_audit(audit);
+        self
+    }
+    
+    // Write users and refresh tokens in the request's transaction
+    pub fn with_unit_of_work(mut self, unit_of_work: Option<Arc<UnitOfWork>>) -> Self {
+        self.users = self.users.with_unit_of_work(unit_of_work.clone());
+        self.unit_of_work = unit_of_work;
+        self
+    }
+    
+    pub async fn register(&self, input: RegisterInput) -> ServiceResult<AuthPayload> {
+        let mut v = Validation::of(&input);
+        v.check("email", "is already registered", self.users.email_available(&input.email, None)).await?;
+        v.finish()?;
+        
+        // Argon2 tốn CPU, chạy ngoài các worker async
+        let password = input.password;
+        let password_hash = tokio::task::spawn_blocking(move || hash_password(&password)).await??;
+        
+        let mut user = User::new(input.name, input.email, input.age);
+        user.password_hash = Some(password_hash);
+        let user = self.users.insert_user(user).await?;
+        
+        self.issue_tokens(user).await
+    }
+    
+    pub async fn login(&self, email: &str, password: String) -> ServiceResult<AuthPayload> {
+        let invalid = || ServiceError::AuthenticationError("Invalid email or password".to_string());
+        
+        let user = self.users.find_by_email(email).await?;
+        let hash = user.as_ref().and_then(|user| user.password_hash.clone());
+        
+        // Without a user the password is checked against a dummy hash, so an
+        // unknown email takes as long to reject as a wrong password
+        let valid = tokio::task::spawn_blocking(move || match hash {
+            Some(hash) => verify_password(&password, &hash),
+            None => {
+                verify_password(&password, dummy_password_hash());
+                false
+            }
+        })
+        .await?;
+        
+        match user {
+            Some(user) if valid => self.issue_tokens(user).await,
+            _ => Err(invalid()),
+        }
+    }
+    
+    // Refresh tokens are single use: the presented token is revoked and a new pair issued
+    pub async fn refresh(&self, refresh_token: &str) -> ServiceResult<AuthPayload> {
+        let claims = self.jwt.decode(refresh_token, TokenType::Refresh)?;
+        
+        let filter = doc! { "_id": &claims.jti, "revoked": false };
+        let update = doc! { "$set": { "revoked": true } };
+        let revoked = match &self.unit_of_work {
+            Some(uow) => {
+                self.refresh_tokens
+                    .find_one_and_update_with_session(filter, update, None, &mut *uow.session().await?)
+                    .await?
+            }
+            None => self.refresh_tokens.find_one_and_update(filter, update, None).await?,
+        };
+        
+        if revoked.is_none() {
+            return Err(ServiceError::AuthenticationError("Refresh token has been revoked".to_string()));
+        }
+        
+        let user = self.users.find_by_id(&claims.sub).await?
+            .ok_or_else(|| ServiceError::AuthenticationError("User no longer exists".to_string()))?;
+        
+        self.issue_tokens(user).await
+    }
+    
+    pub async fn logout(&self, refresh_token: &str) -> ServiceResult<bool> {
+        let claims = self.jwt.decode(refresh_token, TokenType::Refresh)?;
+        
+        let filter = doc! { "_id": &claims.jti, "revoked": false };
+        let update = doc! { "$set": { "revoked": true } };
+        let result = match &self.unit_of_work {
+            Some(uow) => {
+                self.refresh_tokens
+                    .update_one_with_session(filter, update, None, &mut *uow.session().await?)
+                    .await?
+            }
+            None => self.refresh_tokens.update_one(filter, update, None).await?,
+        };
+        
+        Ok(result.modified_count > 0)
+    }
+    
+    async fn issue_tokens(&self, user: User) -> ServiceResult<AuthPayload> {
+        let user_id = user.base.id
+            .map(|id| id.to_hex())
+            .ok_or_else(|| ServiceError::InternalServerError("User has no id".to_string()))?;
+        
+        let access = self.jwt.issue_access_token(&user_id, &user.roles, &user.permissions)?;
+        let refresh = self.jwt.issue_refresh_token(&user_id)?;
+        
+        let record = RefreshTokenRecord {
+            jti: refresh.claims.jti.clone(),
+            user_id,
+            expires_at: DateTime::from_millis(refresh.claims.exp * 1000),
+            revoked: false,
+        };
+        match &self.unit_of_work {
+            Some(uow) => {
+                self.refresh_tokens
+                    .insert_one_with_session(record, None, &mut *uow.session().await?)
+                    .await?;
+            }
+            None => {
+                self.refresh_tokens.insert_one(record, None).await?;
+            }
+        }
+        
+        Ok(AuthPayload {
+            access_token: access.token,
+            refresh_token: refresh.token,
+            token_type: "Bearer".to_string(),
+            expires_in: self.jwt.access_token_ttl(),
+            user,
+        })
+    }
+}
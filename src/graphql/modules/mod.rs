@@ -1,2 +1,5 @@
 // Exports các module con
+pub mod audit;
+pub mod auth;
+pub mod persisted_query;
 pub mod user;
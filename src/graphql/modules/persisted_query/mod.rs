@@ -0,0 +1,2 @@
+// Export các thành phần trong module persisted_query
+pub mod resolver;
@@ -0,0 +1,32 @@
+use async_graphql::{Context, Object};
+
+use crate::base::auth::Role;
+use crate::base::error::ServiceError;
+use crate::base::guard::RoleGuard;
+use crate::graphql::persisted_queries::{query_hash, SharedQueryStore};
+
+#[derive(Default)]
+pub struct PersistedQueryMutation;
+
+#[Object]
+impl PersistedQueryMutation {
+    // Store a query ahead of time so clients can run it by hash, and so it
+    // passes the allowlist. Returns the sha256 hash clients send.
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    pub async fn register_persisted_query(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+    ) -> Result<String, async_graphql::Error> {
+        let store = ctx
+            .data_opt::<SharedQueryStore>()
+            .ok_or_else(|| ServiceError::ValidationError("Persisted queries are disabled".to_string()))?;
+
+        async_graphql::parser::parse_query(&query)
+            .map_err(|e| ServiceError::ValidationError(format!("Invalid query: {}", e)))?;
+
+        let hash = query_hash(&query);
+        store.register(&hash, &query).await?;
+        Ok(hash)
+    }
+}
@@ -1,56 +1,173 @@
-use async_graphql::{SimpleObject, ID};
+use async_graphql::{ComplexObject, Enum, InputObject, SimpleObject, ID};
 use serde::{Serialize, Deserialize};
 use mongodb::bson::doc;
 
+use crate::base::auth::{default_roles, Permission, Role};
+use crate::base::base_event::EventType;
+use crate::base::base_model::BaseModel;
+use crate::base::guard::OwnerOrRole;
+use crate::base::filter::{DateTimeFilter, IdFilter, IntFilter, StringFilter};
+use crate::base::validation::{Validate, Validation};
+use crate::constants::validation::{AGE_MAX, AGE_MIN, EMAIL_MAX_LENGTH, NAME_MAX_LENGTH};
+use crate::db::indexes::{IndexSpec, ModelIndexes};
+
 #[derive(SimpleObject, Serialize, Deserialize, Clone)]
+#[graphql(complex)]
 pub struct User {
-    // Sử dụng serde để bỏ qua trường này khi nó là None
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "_id", with = "crate::graphql::modules::user::model::bson_id_as_string")]
-    pub id: Option<ID>,
+    // `_id`, status và timestamps dùng chung cho mọi model
+    #[serde(flatten)]
+    #[graphql(flatten)]
+    pub base: BaseModel,
     pub name: String,
+    // Chỉ chủ tài khoản hoặc admin mới xem được email, xem ComplexObject bên dưới
+    #[graphql(skip)]
     pub email: String,
     pub age: Option<i32>,
+    #[serde(default = "default_roles")]
+    pub roles: Vec<Role>,
+    // Quyền được cấp trực tiếp, ngoài các quyền có sẵn theo role
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+    // Hash Argon2 của mật khẩu, không bao giờ được trả về qua GraphQL
+    #[graphql(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_hash: Option<String>,
 }
 
-// Helper module để chuyển đổi ObjectId <-> String
-pub mod bson_id_as_string {
-    use mongodb::bson::oid::ObjectId;
-    use serde::{self, Deserialize, Serializer, Deserializer};
-    use async_graphql::ID;
-
-    pub fn serialize<S>(id: &Option<ID>, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        match id {
-            Some(id) => serializer.serialize_str(&id),
-            None => serializer.serialize_none(),
-        }
-    }
-
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<ID>, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let id = Option::<String>::deserialize(deserializer)?;
-        match id {
-            Some(id) => {
-                let oid = ObjectId::parse_str(&id).map_err(serde::de::Error::custom)?;
-                Ok(Some(ID(oid.to_string())))
-            }
-            None => Ok(None),
-        }
+// Email là duy nhất, find_by_email và đăng nhập dựa vào điều này
+impl ModelIndexes for User {
+    fn indexes() -> Vec<IndexSpec> {
+        vec![IndexSpec::new(doc! { "email": 1 }).name("email_unique").unique()]
     }
 }
 
 impl User {
     pub fn new(name: String, email: String, age: Option<i32>) -> Self {
         Self {
-            id: None,
+            base: BaseModel::new(),
             name,
             email,
             age,
+            roles: default_roles(),
+            permissions: Vec::new(),
+            password_hash: None,
         }
     }
 }
+
+#[ComplexObject]
+impl User {
+    // private: kết quả phụ thuộc người xem nên response cache phải tách theo từng user
+    #[graphql(
+        guard = "OwnerOrRole::new(self.base.graphql_id().as_ref(), Role::Admin)",
+        cache_control(private)
+    )]
+    async fn email(&self) -> Option<&str> {
+        Some(&self.email)
+    }
+}
+
+// Input cho mutation createUser
+#[derive(InputObject)]
+pub struct CreateUserInput {
+    pub name: String,
+    pub email: String,
+    pub age: Option<i32>,
+}
+
+impl Validate for CreateUserInput {
+    fn validate(&self, v: &mut Validation) {
+        v.string("name", &self.name).not_blank().max_length(NAME_MAX_LENGTH);
+        v.string("email", &self.email).not_blank().max_length(EMAIL_MAX_LENGTH).email();
+        v.int("age", &self.age).range(AGE_MIN, AGE_MAX);
+    }
+}
+
+// Input cho mutation updateUser, chỉ các trường được truyền mới bị thay đổi
+#[derive(InputObject, Default)]
+pub struct UpdateUserInput {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub age: Option<i32>,
+}
+
+impl Validate for UpdateUserInput {
+    fn validate(&self, v: &mut Validation) {
+        v.string("name", &self.name).not_blank().max_length(NAME_MAX_LENGTH);
+        v.string("email", &self.email).not_blank().max_length(EMAIL_MAX_LENGTH).email();
+        v.int("age", &self.age).range(AGE_MIN, AGE_MAX);
+    }
+}
+
+// Bộ lọc cho các truy vấn danh sách user, chỉ các trường khai báo ở đây mới được phép lọc.
+// Không có email: lọc theo tiền tố hoặc regex sẽ dò ra email mà người xem không được thấy
+crate::model_filter!(UserFilterInput {
+    id: IdFilter => "_id",
+    name: StringFilter => "name",
+    age: IntFilter => "age",
+    created_at: DateTimeFilter => "created_at",
+    updated_at: DateTimeFilter => "updated_at",
+});
+
+// Các trường được phép sắp xếp, email bị loại vì cùng lý do với bộ lọc
+crate::model_sort!(UserSortInput, UserSortField {
+    Id => "_id",
+    Name => "name",
+    Age => "age",
+    CreatedAt => "created_at",
+    UpdatedAt => "updated_at",
+});
+
+// Kết quả trả về của mutation deleteUsers
+#[derive(SimpleObject)]
+pub struct DeleteUsersResult {
+    pub deleted_count: u64,
+    pub ids: Vec<ID>,
+}
+
+// Loại thay đổi được gửi qua subscription userChanged
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum ChangeType {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl ChangeType {
+    pub fn from_event_type(event_type: &EventType) -> Option<Self> {
+        match event_type {
+            EventType::Created => Some(ChangeType::Created),
+            EventType::Updated => Some(ChangeType::Updated),
+            EventType::Deleted => Some(ChangeType::Deleted),
+            EventType::Custom(_) => None,
+        }
+    }
+}
+
+// Payload của subscription userChanged
+#[derive(SimpleObject, Clone)]
+pub struct UserChangedEvent {
+    pub change_type: ChangeType,
+    pub user: User,
+}
+
+// Bộ lọc cho subscription userChanged, bỏ trống để nhận mọi thay đổi
+#[derive(InputObject, Default)]
+pub struct UserChangedFilter {
+    pub change_types: Option<Vec<ChangeType>>,
+    pub ids: Option<Vec<ID>>,
+}
+
+impl UserChangedFilter {
+    pub fn matches(&self, event: &UserChangedEvent) -> bool {
+        let type_matches = self
+            .change_types
+            .as_ref()
+            .is_none_or(|types| types.contains(&event.change_type));
+        let id_matches = self
+            .ids
+            .as_ref()
+            .is_none_or(|ids| event.user.base.graphql_id().is_some_and(|id| ids.contains(&id)));
+        type_matches && id_matches
+    }
+}
@@ -1,27 +1,108 @@
-use crate::graphql::modules::user::model::User;
+use std::sync::Arc;
+
+use crate::base::audit::AuditTrail;
+use crate::base::auth::{Permission, Role};
+use crate::base::base_event::EventBus;
+use crate::base::base_model::ModelId;
+use crate::base::base_service::BaseService;
+use crate::base::error::ServiceError;
+use crate::base::guard::{GuardIf, OwnerOrPermission, PermissionGuard, RoleGuard};
+use crate::base::filter::{sort_document, ModelFilter};
+use crate::base::pagination::{list_complexity, page_size, ModelConnection, PageArgs};
+use crate::base::validation::Validation;
+use crate::constants::pagination::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::db::transaction::UnitOfWork;
+use crate::graphql::loaders::UserLoader;
+use crate::graphql::modules::user::model::{
+    ChangeType, CreateUserInput, DeleteUsersResult, UpdateUserInput, User, UserChangedEvent, UserChangedFilter,
+    UserFilterInput, UserSortInput,
+};
 use crate::graphql::modules::user::service::UserService;
 use crate::graphql::schema::DbContext;
-use async_graphql::{Context, Object, Result, ID};
+use async_graphql::{connection, Context, Object, Result, Subscription, ID};
+use futures::{Stream, StreamExt};
 
 #[derive(Default)]
 pub struct UserQuery;
 
 #[Object]
 impl UserQuery {
-    pub async fn users(&self, ctx: &Context<'_>) -> Result<Vec<User>, async_graphql::Error> {
-        let db_ctx = ctx.data::<DbContext>().expect("Cannot get DB context");
-        let user_service = UserService::new(&db_ctx.db);
-        Ok(user_service.find_all().await)
+    #[graphql(
+        guard = "PermissionGuard::new(Permission::UsersRead).and(GuardIf::new(include_deleted, RoleGuard::new(Role::Admin)))",
+        complexity = "list_complexity(page_size(limit, MAX_PAGE_SIZE), child_complexity)",
+        cache_control(max_age = 60)
+    )]
+    pub async fn users(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<UserFilterInput>,
+        sort: Option<Vec<UserSortInput>>,
+        #[graphql(default)] include_deleted: bool,
+        // At most MAX_PAGE_SIZE users are returned
+        limit: Option<i32>,
+    ) -> Result<Vec<User>, async_graphql::Error> {
+        // MongoDB treats a limit of 0 as no limit at all
+        let mut validation = Validation::new();
+        validation.int("limit", &limit).min(1);
+        validation.finish()?;
+
+        let filter = filter.unwrap_or_default().to_document()?;
+        let sort = sort_document(&sort.unwrap_or_default());
+        let limit = page_size(limit, MAX_PAGE_SIZE) as i64;
+
+        let db_ctx = ctx.data::<DbContext>()?;
+        let user_service = UserService::new(&db_ctx.db).with_deleted(include_deleted);
+        Ok(user_service.find_many_sorted(filter, Some(sort), Some(limit), None).await?)
+    }
+
+    #[graphql(
+        guard = "PermissionGuard::new(Permission::UsersRead).and(GuardIf::new(include_deleted, RoleGuard::new(Role::Admin)))",
+        complexity = "list_complexity(page_size(first.or(last), DEFAULT_PAGE_SIZE), child_complexity)",
+        cache_control(max_age = 60)
+    )]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn users_connection(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<UserFilterInput>,
+        #[graphql(default)] include_deleted: bool,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<ModelConnection<User>, async_graphql::Error> {
+        let filter = filter.unwrap_or_default().to_document()?;
+
+        let db_ctx = ctx.data::<DbContext>()?;
+        let user_service = UserService::new(&db_ctx.db).with_deleted(include_deleted);
+        let count_total = ctx.look_ahead().field("totalCount").exists();
+        connection::query(after, before, first, last, |after, before, first, last| async move {
+            let args = PageArgs::new(after, before, first, last)?.with_total_count(count_total);
+            let page = user_service.find_page(filter, args).await?;
+            Ok::<_, async_graphql::Error>(page.into_connection())
+        })
+        .await
     }
 
+    #[graphql(
+        guard = "PermissionGuard::new(Permission::UsersRead).and(GuardIf::new(include_deleted, RoleGuard::new(Role::Admin)))",
+        cache_control(max_age = 60)
+    )]
     pub async fn user(
         &self,
         ctx: &Context<'_>,
         id: ID,
+        #[graphql(default)] include_deleted: bool,
     ) -> Result<Option<User>, async_graphql::Error> {
-        let db_ctx = ctx.data::<DbContext>().expect("Cannot get DB context");
-        let user_service = UserService::new(&db_ctx.db);
-        Ok(user_service.find_by_id(&id.to_string()).await)
+        // The loader only sees active users, deleted ones are read directly
+        if include_deleted {
+            let db_ctx = ctx.data::<DbContext>()?;
+            let user_service = UserService::new(&db_ctx.db).with_deleted(true);
+            return Ok(user_service.find_by_id(&id).await?);
+        }
+
+        let loader = ctx.data::<UserLoader>()?;
+        Ok(loader.load_one(id.to_object_id()?).await?)
     }
 }
 
@@ -30,16 +111,153 @@ pub struct UserMutation;
 
 #[Object]
 impl UserMutation {
+    #[graphql(guard = "PermissionGuard::new(Permission::UsersWrite)")]
     pub async fn create_user(
         &self,
         ctx: &Context<'_>,
-        name: String,
-        email: String,
-        age: Option<i32>,
-    ) -> Result<User, String> {
-        let db_ctx = ctx.data::<DbContext>().expect("Cannot get DB context");
-        let user_service = UserService::new(&db_ctx.db);
-        user_service.create_user(name, email, age).await
-            .map_err(|e| e.to_string())
+        input: CreateUserInput,
+    ) -> Result<User, async_graphql::Error> {
+        let user_service = mutation_service(ctx)?;
+        Ok(user_service.create_user(input).await?)
+    }
+
+    #[graphql(guard = "OwnerOrPermission::new(Some(&id), Permission::UsersWrite)")]
+    pub async fn update_user(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+        input: UpdateUserInput,
+        // Fail with CONFLICT if the user changed since this version was read
+        expected_version: Option<i64>,
+    ) -> Result<User, async_graphql::Error> {
+        let user_service = mutation_service(ctx)?;
+        Ok(user_service.update_user(&id, input, expected_version).await?)
+    }
+
+    // Soft delete, reversible with restoreUser until the user is purged
+    #[graphql(guard = "OwnerOrPermission::new(Some(&id), Permission::UsersDelete)")]
+    pub async fn delete_user(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+        expected_version: Option<i64>,
+    ) -> Result<User, async_graphql::Error> {
+        let user_service = mutation_service(ctx)?;
+        Ok(user_service.delete_user(&id, expected_version).await?)
+    }
+
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    pub async fn restore_user(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+    ) -> Result<User, async_graphql::Error> {
+        let user_service = mutation_service(ctx)?;
+        Ok(user_service.restore_user(&id).await?)
+    }
+
+    // Permanently remove a user, deleted or not
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    pub async fn purge_user(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+    ) -> Result<User, async_graphql::Error> {
+        let user_service = mutation_service(ctx)?;
+        Ok(user_service.purge_user(&id).await?)
+    }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::UsersDelete)")]
+    pub async fn delete_users(
+        &self,
+        ctx: &Context<'_>,
+        filter: UserFilterInput,
+    ) -> Result<DeleteUsersResult, async_graphql::Error> {
+        // Không cho phép bộ lọc rỗng để tránh xoá toàn bộ collection
+        let filter = filter.to_document()?;
+        if filter.is_empty() {
+            return Err(ServiceError::ValidationError("deleteUsers requires a non-empty filter".to_string()).into());
+        }
+
+        let user_service = mutation_service(ctx)?;
+        let ids = user_service.delete_users(filter).await?;
+
+        Ok(DeleteUsersResult {
+            deleted_count: ids.len() as u64,
+            ids,
+        })
+    }
+
+    // Replace the roles and directly granted permissions of a user
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    pub async fn set_user_roles(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+        roles: Vec<Role>,
+        permissions: Option<Vec<Permission>>,
+    ) -> Result<User, async_graphql::Error> {
+        let user_service = mutation_service(ctx)?;
+        Ok(user_service.set_roles(&id, roles, permissions.unwrap_or_default()).await?)
+    }
+}
+
+// Service used by mutations: publishes events, records the audit trail and
+// joins the request's unit of work when the schema runs one
+fn mutation_service(ctx: &Context<'_>) -> Result<UserService> {
+    let db_ctx = ctx.data::<DbContext>()?;
+    let event_bus = ctx.data::<Arc<EventBus>>()?;
+    Ok(UserService::new(&db_ctx.db)
+        .with_events(event_bus.emitter::<User>())
+        .with_audit(AuditTrail::from_context(&db_ctx.db, ctx))
+        .with_unit_of_work(ctx.data_opt::<Arc<UnitOfWork>>().cloned()))
+}
+
+#[derive(Default)]
+pub struct UserSubscription;
+
+impl UserSubscription {
+    fn changes(&self, ctx: &Context<'_>) -> Result<impl Stream<Item = UserChangedEvent>> {
+        let event_bus = ctx.data::<Arc<EventBus>>()?;
+        Ok(event_bus.emitter::<User>().stream().filter_map(|event| async move {
+            ChangeType::from_event_type(&event.event_type).map(|change_type| UserChangedEvent {
+                change_type,
+                user: event.payload,
+            })
+        }))
+    }
+
+    fn changes_of(&self, ctx: &Context<'_>, change_type: ChangeType) -> Result<impl Stream<Item = User>> {
+        Ok(self.changes(ctx)?.filter_map(move |event| async move {
+            (event.change_type == change_type).then_some(event.user)
+        }))
+    }
+}
+
+#[Subscription]
+impl UserSubscription {
+    #[graphql(guard = "PermissionGuard::new(Permission::UsersRead)")]
+    pub async fn user_created(&self, ctx: &Context<'_>) -> Result<impl Stream<Item = User>> {
+        self.changes_of(ctx, ChangeType::Created)
+    }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::UsersRead)")]
+    pub async fn user_updated(&self, ctx: &Context<'_>) -> Result<impl Stream<Item = User>> {
+        self.changes_of(ctx, ChangeType::Updated)
+    }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::UsersRead)")]
+    pub async fn user_deleted(&self, ctx: &Context<'_>) -> Result<impl Stream<Item = User>> {
+        self.changes_of(ctx, ChangeType::Deleted)
+    }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::UsersRead)")]
+    pub async fn user_changed(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<UserChangedFilter>,
+    ) -> Result<impl Stream<Item = UserChangedEvent>> {
+        let filter = filter.unwrap_or_default();
+        Ok(self.changes(ctx)?.filter(move |event| futures::future::ready(filter.matches(event))))
     }
 }
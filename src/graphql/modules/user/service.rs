@@ -1,113 +1,271 @@
-use mongodb::{Collection, Database};
-use mongodb::bson::{doc, Document, oid::ObjectId};
-use std::error::Error;
+use std::sync::Arc;
+
 use futures::StreamExt;
+use mongodb::{Collection, Database};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use mongodb::options::{FindOneOptions, FindOptions};
 use async_graphql::ID;
+use async_trait::async_trait;
 
-use crate::graphql::modules::user::model::User;
+use crate::base::audit::AuditTrail;
+use crate::base::auth::{GrantSource, Permission, Role};
+use crate::base::base_event::{EventEmitter, EventType};
+use crate::base::base_model::{ModelId, Status};
+use crate::base::base_service::BaseService;
+use crate::base::crud_service::CrudService;
+use crate::base::error::{ServiceError, ServiceResult};
+use crate::base::validation::Validation;
+use crate::constants::filter::BULK_DELETE_BATCH_SIZE;
+use crate::db::transaction::UnitOfWork;
+use crate::graphql::modules::user::model::{CreateUserInput, UpdateUserInput, User};
 
 pub struct UserService {
     collection: Collection<User>,
+    events: Option<EventEmitter<User>>,
+    include_deleted: bool,
+    unit_of_work: Option<Arc<UnitOfWork>>,
+    audit: Option<AuditTrail>,
 }
 
-impl UserService {
-    pub fn new(db: &Database) -> Self {
-        Self { collection: db.collection("users") }
+// Reads and basic writes come from the generic base traits; UserService only
+// adds user-specific operations and event publishing on top
+#[async_trait]
+impl BaseService<User> for UserService {
+    fn get_collection(&self) -> Collection<User> {
+        self.collection.clone()
+    }
+    
+    fn include_deleted(&self) -> bool {
+        self.include_deleted
+    }
+    
+    fn unit_of_work(&self) -> Option<&UnitOfWork> {
+        self.unit_of_work.as_deref()
     }
     
-    pub async fn find_all(&self) -> Vec<User> {
-        let cursor = self.collection.find(None, None).await.unwrap_or_else(|e| {
-            eprintln!("Query error: {}", e);
-            panic!("Could not get data from MongoDB");
-        });
+    fn audit_trail(&self) -> Option<&AuditTrail> {
+        self.audit.as_ref()
+    }
+}
 
-        let users: Vec<User> = cursor.collect::<Vec<_>>().await.into_iter()
-            .filter_map(Result::ok)
-            .collect();
+#[async_trait]
+impl CrudService<User> for UserService {}
+
+#[async_trait]
+impl GrantSource for UserService {
+    async fn grants(&self, user_id: &str) -> ServiceResult<Option<(Vec<Role>, Vec<Permission>)>> {
+        let user = self.find_by_id(user_id).await?;
+        Ok(user.map(|user| (user.roles, user.permissions)))
+    }
+}
 
-        users
+impl UserService {
+    pub fn new(db: &Database) -> Self {
+        Self { collection: db.collection("users"), events: None, include_deleted: false, unit_of_work: None, audit: None }
     }
     
-    pub async fn find_by_id(&self, id: &str) -> Option<User> {
-        let oid = match ObjectId::parse_str(id) {
-            Ok(oid) => oid,
-            Err(_) => return None,
+    // Also read soft deleted users (admin views)
+    pub fn with_deleted(mut self, include_deleted: bool) -> Self {
+        self.include_deleted = include_deleted;
+        self
+    }
+    
+    // Publish Created/Updated/Deleted events for every write made through this service
+    pub fn with_events(mut self, events: EventEmitter<User>) -> Self {
+        self.events = Some(events);
+        self
+    }
+    
+    // Record every write in the audit log
+    pub fn with_audit(mut self, audit: AuditTrail) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+    
+    // Run every read and write in the request's transaction, see db::transaction
+    pub fn with_unit_of_work(mut self, unit_of_work: Option<Arc<UnitOfWork>>) -> Self {
+        self.unit_of_work = unit_of_work;
+        self
+    }
+    
+    // Inside a transaction events wait for the commit, so subscribers never
+    // see a write that is rolled back
+    fn emit(&self, event_type: EventType, user: &User) {
+        let Some(events) = &self.events else {
+            return;
         };
-
-        self.collection.find_one(doc! { "_id": oid }, None).await.unwrap_or(None)
+        match &self.unit_of_work {
+            Some(uow) => {
+                let (events, user) = (events.clone(), user.clone());
+                uow.after_commit(move || events.emit_local(event_type, user));
+            }
+            None => events.emit_local(event_type, user.clone()),
+        }
     }
     
-    pub async fn create_user(&self, name: String, email: String, age: Option<i32>) -> Result<User, Box<dyn Error>> {
-        let user = User::new(name, email, age);
+    pub async fn create_user(&self, input: CreateUserInput) -> ServiceResult<User> {
+        let mut v = Validation::of(&input);
+        v.check("email", "is already taken", self.email_available(&input.email, None)).await?;
+        v.finish()?;
         
-        // Directly implement create functionality instead of calling the trait method
-        let result = self.collection.insert_one(user.clone(), None).await
-            .map_err(|e| Box::new(std::io::Error::new(std::io::ErrorKind::Other, e)) as Box<dyn Error>)?;
-
-        let id = result.inserted_id.as_object_id().unwrap();
-        let mut new_user = user.clone();
-        new_user.id = Some(ID(id.to_string()));
-        Ok(new_user)
+        self.insert_user(User::new(input.name, input.email, input.age)).await
+    }
+    
+    pub async fn insert_user(&self, user: User) -> ServiceResult<User> {
+        let user = self.create(user).await?;
+        self.emit(EventType::Created, &user);
+        Ok(user)
     }
     
-    pub async fn update_user(&self, id: &str, name: Option<String>, email: Option<String>, age: Option<i32>) -> Result<Option<User>, Box<dyn Error>> {
+    pub async fn update_user(&self, id: &str, input: UpdateUserInput, expected_version: Option<i64>) -> ServiceResult<User> {
+        let mut v = Validation::of(&input);
+        if let Some(email) = &input.email {
+            v.check("email", "is already taken", self.email_available(email, Some(id))).await?;
+        }
+        v.finish()?;
+        
         let mut update_doc = Document::new();
         
-        if let Some(name) = name {
+        if let Some(name) = input.name {
             update_doc.insert("name", name);
         }
         
-        if let Some(email) = email {
+        if let Some(email) = input.email {
             update_doc.insert("email", email);
         }
         
-        if let Some(age) = age {
+        if let Some(age) = input.age {
             update_doc.insert("age", age);
         }
         
-        // Directly implement update functionality
-        let object_id = ObjectId::parse_str(id)
-            .map_err(|_| Box::new(std::io::Error::new(std::io::ErrorKind::Other, format!("Invalid ID: {}", id))) as Box<dyn Error>)?;
-            
-        update_doc.insert("updated_at", mongodb::bson::DateTime::now());
+        if update_doc.is_empty() {
+            return Err(ServiceError::ValidationError("No fields to update".to_string()));
+        }
         
-        let update = doc! { "$set": update_doc };
+        self.update_and_emit(id, update_doc, expected_version).await
+    }
+    
+    pub async fn set_roles(&self, id: &str, roles: Vec<Role>, permissions: Vec<Permission>) -> ServiceResult<User> {
+        let update_doc = doc! {
+            "roles": mongodb::bson::to_bson(&roles)?,
+            "permissions": mongodb::bson::to_bson(&permissions)?,
+        };
         
-        let _ = self.collection
-            .update_one(doc! { "_id": object_id }, update, None)
-            .await
-            .map_err(|e| Box::new(std::io::Error::new(std::io::ErrorKind::Other, e)) as Box<dyn Error>)?;
-            
-        Ok(self.find_by_id(id).await)
+        self.update_and_emit(id, update_doc, None).await
     }
     
-    pub async fn find_by_email(&self, email: &str) -> Result<Option<User>, Box<dyn Error>> {
-        let filter = doc! { "email": email };
+    async fn update_and_emit(&self, id: &str, update_doc: Document, expected_version: Option<i64>) -> ServiceResult<User> {
+        let user = self.update(id, update_doc, expected_version).await?
+            .ok_or_else(|| ServiceError::NotFound(format!("User {} not found", id)))?;
         
-        // Directly implement find_many functionality
-        let mut options = mongodb::options::FindOptions::default();
-        options.limit = Some(1);
+        self.emit(EventType::Updated, &user);
+        Ok(user)
+    }
+    
+    // Soft delete: the user is hidden from reads but can still be restored
+    pub async fn delete_user(&self, id: &str, expected_version: Option<i64>) -> ServiceResult<User> {
+        let user = self.soft_delete(id, expected_version).await?
+            .ok_or_else(|| ServiceError::NotFound(format!("User {} not found", id)))?;
         
-        let mut cursor = self.collection
-            .find(filter, options)
-            .await
-            .map_err(|e| Box::new(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())) as Box<dyn Error>)?;
+        self.emit(EventType::Deleted, &user);
+        Ok(user)
+    }
+    
+    // Soft delete every user matching `filter`, BULK_DELETE_BATCH_SIZE at a
+    // time in `_id` order, and return the ids of the users deleted
+    pub async fn delete_users(&self, filter: Document) -> ServiceResult<Vec<ID>> {
+        let mut deleted_ids = Vec::new();
+        let mut last_id: Option<ObjectId> = None;
+        loop {
+            let page_filter = match last_id {
+                Some(last_id) => doc! { "$and": [filter.clone(), { "_id": { "$gt": last_id } }] },
+                None => filter.clone(),
+            };
+            let batch = self.find_many_sorted(page_filter, Some(doc! { "_id": 1 }), Some(BULK_DELETE_BATCH_SIZE), None).await?;
+            let object_ids = batch.iter().filter_map(|user| user.base.id).collect::<Vec<_>>();
+            let Some(&batch_last_id) = object_ids.last() else {
+                break;
+            };
+            
+            self.soft_delete_many(doc! { "_id": { "$in": &object_ids } }).await?;
             
-        let mut results = Vec::new();
-        while let Some(result) = cursor.next().await {
-            match result {
-                Ok(document) => results.push(document),
-                Err(e) => return Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))),
+            // Events carry the users as written, not the copies read before the write
+            for user in self.find_deleted(&object_ids).await? {
+                self.emit(EventType::Deleted, &user);
+                deleted_ids.extend(user.base.id.map(|id| ID(id.to_hex())));
             }
-        }
             
-        Ok(results.into_iter().next())
+            if (batch.len() as i64) < BULK_DELETE_BATCH_SIZE {
+                break;
+            }
+            last_id = Some(batch_last_id);
+        }
+        Ok(deleted_ids)
+    }
+    
+    // Read back soft deleted users by id, which `scope` would hide
+    async fn find_deleted(&self, ids: &[ObjectId]) -> ServiceResult<Vec<User>> {
+        let filter = doc! { "_id": { "$in": ids }, "status": Status::Deleted.code() };
+        let options = FindOptions::builder().sort(doc! { "_id": 1 }).build();
+        let mut users = Vec::new();
+        match &self.unit_of_work {
+            Some(uow) => {
+                let mut session = uow.session().await?;
+                let mut cursor = self.collection.find_with_session(filter, options, &mut session).await?;
+                while let Some(user) = cursor.next(&mut session).await {
+                    users.push(user?);
+                }
+            }
+            None => {
+                let mut cursor = self.collection.find(filter, options).await?;
+                while let Some(user) = cursor.next().await {
+                    users.push(user?);
+                }
+            }
+        }
+        Ok(users)
+    }
+    
+    pub async fn restore_user(&self, id: &str) -> ServiceResult<User> {
+        let user = self.restore(id).await?
+            .ok_or_else(|| ServiceError::NotFound(format!("Deleted user {} not found", id)))?;
+        
+        self.emit(EventType::Updated, &user);
+        Ok(user)
+    }
+    
+    // Hard delete; subscribers already saw the Deleted event if the user was soft deleted
+    pub async fn purge_user(&self, id: &str) -> ServiceResult<User> {
+        let user = self.purge(id).await?
+            .ok_or_else(|| ServiceError::NotFound(format!("User {} not found", id)))?;
+        
+        if !user.base.is_deleted() {
+            self.emit(EventType::Deleted, &user);
+        }
+        Ok(user)
+    }
+    
+    pub async fn find_by_email(&self, email: &str) -> ServiceResult<Option<User>> {
+        self.find_one(doc! { "email": email }).await
+    }
+    
+    // Async validator: no other user already uses `email`. Soft deleted users
+    // keep their email reserved until purged, as the unique index does.
+    pub async fn email_available(&self, email: &str, except: Option<&str>) -> ServiceResult<bool> {
+        let mut filter = doc! { "email": email };
+        if let Some(id) = except {
+            filter.insert("_id", doc! { "$ne": id.to_object_id()? });
+        }
+        let collection = self.collection.clone_with_type::<Document>();
+        let options = FindOneOptions::builder().projection(doc! { "_id": 1 }).build();
+        let existing = match &self.unit_of_work {
+            Some(uow) => collection.find_one_with_session(filter, options, &mut *uow.session().await?).await?,
+            None => collection.find_one(filter, options).await?,
+        };
+        Ok(existing.is_none())
     }
     
-    pub async fn count_all(&self) -> Result<u64, Box<dyn Error>> {
-        self.collection.count_documents(doc! {}, None).await
-            .map_err(|e| Box::new(std::io::Error::new(std::io::ErrorKind::Other, e)) as Box<dyn Error>)
+    pub async fn count_all(&self) -> ServiceResult<u64> {
+        self.count(doc! {}).await
     }
 }
-
-// BaseService and CrudService implementations removed to simplify service structure
@@ -0,0 +1,419 @@
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+use std::time::Duration;
+
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest, NextValidation,
+};
+use async_graphql::{ErrorExtensionValues, Pos, Request, ServerError, ServerResult, ValidationResult};
+use lru::LruCache;
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::UpdateOptions;
+use mongodb::{Collection, Database};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::base::auth::{request_user, Role};
+use crate::base::error::{ServiceError, ServiceResult};
+use crate::configs::config::AppConfig;
+use crate::constants::persisted_query::{
+    MAX_PERSISTED_QUERY_LENGTH, PERSISTED_QUERY_COLLECTION, PERSISTED_QUERY_TTL_SECONDS, PERSISTED_QUERY_VERSION,
+};
+use crate::db::indexes::{IndexSpec, ModelIndexes};
+
+// Where persisted queries are kept, keyed by the sha256 hex digest of the
+// query text. Queries are stored as text and parsed on every run so they go
+// through the same parse hooks (see graphql::limits) as any other query.
+#[async_trait::async_trait]
+pub trait PersistedQueryStore: Send + Sync {
+    async fn get(&self, hash: &str) -> ServiceResult<Option<String>>;
+
+    // Query stored by a client, which a store may expire
+    async fn set(&self, hash: &str, query: &str) -> ServiceResult<()>;
+
+    // Query registered by an admin, kept until removed by hand
+    async fn register(&self, hash: &str, query: &str) -> ServiceResult<()> {
+        self.set(hash, query).await
+    }
+}
+
+pub type SharedQueryStore = Arc<dyn PersistedQueryStore>;
+
+// Store picked by the config, None when APQ is turned off. The allowlist
+// needs the MongoDB store: the in-process one forgets registered queries on
+// eviction and restart, which would lock every non-admin out, login included.
+pub fn query_store(config: &AppConfig, db: &Database) -> ServiceResult<Option<SharedQueryStore>> {
+    if !config.apq_enabled() {
+        return Ok(None);
+    }
+    let cache = LruQueryStore::new(config.apq_cache_size());
+    Ok(Some(match config.apq_store() {
+        "mongodb" => Arc::new(MongoQueryStore::new(db, cache)),
+        _ if config.apq_allowlist_only() => {
+            return Err(ServiceError::InternalServerError(
+                "APQ_ALLOWLIST_ONLY requires APQ_STORE=mongodb".to_string(),
+            ))
+        }
+        _ => Arc::new(cache),
+    }))
+}
+
+pub fn query_hash(query: &str) -> String {
+    format!("{:x}", Sha256::digest(query.as_bytes()))
+}
+
+// In-process store, least recently used queries are evicted first
+pub struct LruQueryStore {
+    cache: Mutex<LruCache<String, String>>,
+}
+
+impl LruQueryStore {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self { cache: Mutex::new(LruCache::new(capacity)) }
+    }
+}
+
+#[async_trait::async_trait]
+impl PersistedQueryStore for LruQueryStore {
+    async fn get(&self, hash: &str) -> ServiceResult<Option<String>> {
+        let mut cache = self.cache.lock().map_err(|e| ServiceError::InternalServerError(e.to_string()))?;
+        Ok(cache.get(hash).cloned())
+    }
+
+    async fn set(&self, hash: &str, query: &str) -> ServiceResult<()> {
+        let mut cache = self.cache.lock().map_err(|e| ServiceError::InternalServerError(e.to_string()))?;
+        cache.put(hash.to_string(), query.to_string());
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistedQueryRecord {
+    #[serde(rename = "_id")]
+    pub hash: String,
+    pub query: String,
+    pub created_at: DateTime,
+    // Set on queries stored by clients, registered queries never expire
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime>,
+}
+
+impl ModelIndexes for PersistedQueryRecord {
+    fn indexes() -> Vec<IndexSpec> {
+        vec![IndexSpec::new(doc! { "expires_at": 1 }).name("expires_at_ttl").ttl(Duration::ZERO)]
+    }
+}
+
+// Store shared by every instance, read through an LRU cache. The collection
+// is the source of truth, so queries evicted from the cache are only a lookup
+// away and registered queries are never lost. Required by the allowlist mode.
+pub struct MongoQueryStore {
+    collection: Collection<PersistedQueryRecord>,
+    cache: LruQueryStore,
+}
+
+impl MongoQueryStore {
+    pub fn new(db: &Database, cache: LruQueryStore) -> Self {
+        Self { collection: db.collection(PERSISTED_QUERY_COLLECTION), cache }
+    }
+}
+
+#[async_trait::async_trait]
+impl PersistedQueryStore for MongoQueryStore {
+    async fn get(&self, hash: &str) -> ServiceResult<Option<String>> {
+        if let Some(query) = self.cache.get(hash).await? {
+            return Ok(Some(query));
+        }
+
+        let Some(record) = self.collection.find_one(doc! { "_id": hash }, None).await? else {
+            return Ok(None);
+        };
+        self.cache.set(hash, &record.query).await?;
+        Ok(Some(record.query))
+    }
+
+    async fn set(&self, hash: &str, query: &str) -> ServiceResult<()> {
+        // The hash identifies the text, so a stored query never changes, and
+        // a registered one keeps not expiring
+        let expires_at = DateTime::from_millis(
+            DateTime::now().timestamp_millis() + PERSISTED_QUERY_TTL_SECONDS as i64 * 1000,
+        );
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.collection
+            .update_one(
+                doc! { "_id": hash },
+                doc! { "$setOnInsert": { "query": query, "created_at": DateTime::now(), "expires_at": expires_at } },
+                options,
+            )
+            .await?;
+        self.cache.set(hash, query).await
+    }
+
+    async fn register(&self, hash: &str, query: &str) -> ServiceResult<()> {
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.collection
+            .update_one(
+                doc! { "_id": hash },
+                doc! {
+                    "$setOnInsert": { "query": query, "created_at": DateTime::now() },
+                    "$unset": { "expires_at": "" },
+                },
+                options,
+            )
+            .await?;
+        self.cache.set(hash, query).await
+    }
+}
+
+#[derive(Deserialize)]
+struct PersistedQuery {
+    version: i32,
+    #[serde(rename = "sha256Hash")]
+    sha256_hash: String,
+}
+
+// Apollo's automatic persisted queries protocol. A request carrying only the
+// `persistedQuery` extension runs the stored query or fails with
+// PersistedQueryNotFound, and the client then resends the hash along with the
+// query text to store it.
+//
+// A query sent along with its hash is only stored once it has been parsed
+// and validated, so clients can't fill the store with arbitrary text, and
+// queries longer than MAX_PERSISTED_QUERY_LENGTH are run without being stored.
+//
+// In allowlist mode clients can't store queries: every query, sent as a hash
+// or as text, must already be registered (see registerPersistedQuery). Admins
+// are exempt so they can register new queries.
+pub struct PersistedQueries {
+    store: SharedQueryStore,
+    allowlist_only: bool,
+}
+
+impl PersistedQueries {
+    pub fn new(store: SharedQueryStore, allowlist_only: bool) -> Self {
+        Self { store, allowlist_only }
+    }
+}
+
+impl ExtensionFactory for PersistedQueries {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(PersistedQueriesRequest {
+            store: self.store.clone(),
+            allowlist_only: self.allowlist_only,
+            pending: Mutex::new(None),
+        })
+    }
+}
+
+struct PersistedQueriesRequest {
+    store: SharedQueryStore,
+    allowlist_only: bool,
+    // Hash and text to store once the query has been validated
+    pending: Mutex<Option<(String, String)>>,
+}
+
+impl PersistedQueriesRequest {
+    async fn resolve(&self, ctx: &ExtensionContext<'_>, mut request: Request) -> ServerResult<Request> {
+        let persisted = match request.extensions.remove("persistedQuery") {
+            Some(value) => Some(
+                async_graphql::from_value::<PersistedQuery>(value)
+                    .map_err(|_| ServerError::new("Invalid persistedQuery extension", None))?,
+            ),
+            None => None,
+        };
+        if let Some(persisted) = &persisted {
+            if persisted.version != PERSISTED_QUERY_VERSION {
+                return Err(ServerError::new(
+                    format!("Unsupported persistedQuery version {}", persisted.version),
+                    None,
+                ));
+            }
+        }
+
+        // Hash only: run the stored query
+        if request.query.is_empty() {
+            let Some(persisted) = persisted else {
+                return Ok(request);
+            };
+            return match self.store.get(&persisted.sha256_hash).await.map_err(store_error)? {
+                Some(query) => {
+                    request.query = query;
+                    Ok(request)
+                }
+                None => Err(apq_error("PersistedQueryNotFound", "PERSISTED_QUERY_NOT_FOUND")),
+            };
+        }
+
+        let hash = query_hash(&request.query);
+        if persisted.as_ref().is_some_and(|persisted| persisted.sha256_hash != hash) {
+            return Err(apq_error("provided sha does not match query", "PERSISTED_QUERY_HASH_MISMATCH"));
+        }
+
+        let is_admin = request_user(ctx, &request).is_some_and(|user| user.has_role(Role::Admin));
+        if self.allowlist_only && !is_admin {
+            if self.store.get(&hash).await.map_err(store_error)?.is_none() {
+                return Err(apq_error("Query is not in the allowlist", "PERSISTED_QUERY_NOT_ALLOWED"));
+            }
+        } else if persisted.is_some() && request.query.len() <= MAX_PERSISTED_QUERY_LENGTH {
+            if let Ok(mut pending) = self.pending.lock() {
+                *pending = Some((hash, request.query.clone()));
+            }
+        }
+        Ok(request)
+    }
+}
+
+#[async_trait::async_trait]
+impl Extension for PersistedQueriesRequest {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        let request = self.resolve(ctx, request).await?;
+        next.run(ctx, request).await
+    }
+
+    async fn validation(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextValidation<'_>,
+    ) -> Result<ValidationResult, Vec<ServerError>> {
+        let result = next.run(ctx).await?;
+        let pending = self.pending.lock().ok().and_then(|mut pending| pending.take());
+        if let Some((hash, query)) = pending {
+            self.store.set(&hash, &query).await.map_err(|err| vec![store_error(err)])?;
+        }
+        Ok(result)
+    }
+}
+
+fn apq_error(message: &str, code: &str) -> ServerError {
+    let mut extensions = ErrorExtensionValues::default();
+    extensions.set("code", code);
+
+    let mut error = ServerError::new(message, None);
+    error.extensions = Some(extensions);
+    error
+}
+
+fn store_error(err: ServiceError) -> ServerError {
+    async_graphql::Error::from(err).into_server_error(Pos::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, EmptyMutation, EmptySubscription, Object, Response, Schema};
+
+    use super::*;
+    use crate::base::auth::CurrentUser;
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+        async fn ok(&self) -> bool {
+            true
+        }
+    }
+
+    type TestSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+    fn schema(store: &SharedQueryStore, allowlist_only: bool) -> TestSchema {
+        Schema::build(Query, EmptyMutation, EmptySubscription)
+            .extension(PersistedQueries::new(store.clone(), allowlist_only))
+            .finish()
+    }
+
+    fn request(query: &str, hash: Option<&str>) -> Request {
+        let mut request = Request::new(query);
+        if let Some(hash) = hash {
+            request.extensions.insert("persistedQuery".to_string(), value!({ "version": 1, "sha256Hash": hash }));
+        }
+        request
+    }
+
+    fn code(response: &Response) -> Option<String> {
+        let extensions = response.errors.first()?.extensions.as_ref()?;
+        match extensions.get("code")? {
+            async_graphql::Value::String(code) => Some(code.clone()),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn queries_are_stored_then_run_by_hash() {
+        let store: SharedQueryStore = Arc::new(LruQueryStore::new(10));
+        let schema = schema(&store, false);
+        let query = "{ ok }";
+        let hash = query_hash(query);
+
+        let response = schema.execute(request("", Some(&hash))).await;
+        assert_eq!(code(&response).as_deref(), Some("PERSISTED_QUERY_NOT_FOUND"));
+
+        assert!(schema.execute(request(query, Some(&hash))).await.is_ok());
+        let response = schema.execute(request("", Some(&hash))).await;
+        assert_eq!(response.data, value!({ "ok": true }));
+
+        // Plain queries run without being stored
+        assert!(schema.execute(request("{ a: ok }", None)).await.is_ok());
+        assert_eq!(store.get(&query_hash("{ a: ok }")).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn malformed_extensions_are_rejected() {
+        let store: SharedQueryStore = Arc::new(LruQueryStore::new(10));
+        let schema = schema(&store, false);
+
+        let response = schema.execute(request("{ ok }", Some("not-the-hash"))).await;
+        assert_eq!(code(&response).as_deref(), Some("PERSISTED_QUERY_HASH_MISMATCH"));
+
+        let mut unsupported = Request::new("{ ok }");
+        unsupported.extensions.insert("persistedQuery".to_string(), value!({ "version": 2, "sha256Hash": "x" }));
+        let response = schema.execute(unsupported).await;
+        assert_eq!(response.errors[0].message, "Unsupported persistedQuery version 2");
+
+        let mut invalid = Request::new("{ ok }");
+        invalid.extensions.insert("persistedQuery".to_string(), value!("x"));
+        let response = schema.execute(invalid).await;
+        assert_eq!(response.errors[0].message, "Invalid persistedQuery extension");
+    }
+
+    #[tokio::test]
+    async fn only_valid_queries_of_bounded_length_are_stored() {
+        let store: SharedQueryStore = Arc::new(LruQueryStore::new(10));
+        let schema = schema(&store, false);
+
+        let invalid = "{ missing }";
+        assert!(schema.execute(request(invalid, Some(&query_hash(invalid)))).await.is_err());
+        assert_eq!(store.get(&query_hash(invalid)).await.unwrap(), None);
+
+        let long = format!("{{ ok {} }}", " ".repeat(MAX_PERSISTED_QUERY_LENGTH));
+        assert!(schema.execute(request(&long, Some(&query_hash(&long)))).await.is_ok());
+        assert_eq!(store.get(&query_hash(&long)).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn allowlist_only_runs_registered_queries() {
+        let store: SharedQueryStore = Arc::new(LruQueryStore::new(10));
+        let schema = schema(&store, true);
+        let (registered, other) = ("{ ok }", "{ a: ok }");
+        store.register(&query_hash(registered), registered).await.unwrap();
+
+        assert!(schema.execute(request(registered, None)).await.is_ok());
+        assert!(schema.execute(request("", Some(&query_hash(registered)))).await.is_ok());
+
+        let response = schema.execute(request(other, Some(&query_hash(other)))).await;
+        assert_eq!(code(&response).as_deref(), Some("PERSISTED_QUERY_NOT_ALLOWED"));
+        assert_eq!(store.get(&query_hash(other)).await.unwrap(), None);
+
+        // Admins are exempt and store what they send
+        let admin = CurrentUser { id: "admin".to_string(), roles: vec![Role::Admin], permissions: Vec::new() };
+        let response = schema.execute(request(other, Some(&query_hash(other))).data(admin)).await;
+        assert!(response.is_ok());
+        assert_eq!(store.get(&query_hash(other)).await.unwrap().as_deref(), Some(other));
+    }
+}
@@ -0,0 +1,461 @@
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextExecute, NextParseQuery, NextPrepareRequest, NextValidation,
+};
+use async_graphql::parser::types::{ExecutableDocument, OperationType, Selection, SelectionSet};
+use async_graphql::{CacheControl, Name, Request, Response, ServerError, ServerResult, ValidationResult, Value, Variables};
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+
+use crate::base::auth::{request_user, CurrentUser};
+use crate::base::base_event::EventBus;
+
+// Cache of query responses, sized by `cache_control` hints on fields:
+//
+//     #[graphql(cache_control(max_age = 60))]           shared by viewers with the same access
+//     #[graphql(cache_control(max_age = 60, private))]  cached per user
+//
+// async-graphql merges the hints of the selected fields into the response's
+// Cache-Control header; a response is stored for that max-age, capped by the
+// configured expiration. Queries selecting no hinted field, introspection
+// queries, mutations and responses with errors are never cached.
+//
+// Entries are keyed on the normalized query, operation name, variables and
+// viewer scope. Public entries are still split by roles and permissions
+// since guards make the same query answer differently per access level.
+// Fields whose answer depends on who asks, like those behind an owner
+// guard, must be marked private so the whole response is cached per user.
+#[derive(Clone)]
+pub struct ResponseCache {
+    entries: Arc<Mutex<LruCache<String, CachedResponse>>>,
+    max_ttl: Duration,
+    // Generation counters of the emitters the cache is invalidated by
+    generations: Vec<Arc<dyn Fn() -> u64 + Send + Sync>>,
+}
+
+struct CachedResponse {
+    data: Value,
+    cache_control: CacheControl,
+    expires_at: Instant,
+    // Sum of the emitter generations when the response started executing
+    generation: u64,
+}
+
+impl ResponseCache {
+    pub fn new(capacity: usize, max_ttl_seconds: u64) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            entries: Arc::new(Mutex::new(LruCache::new(capacity))),
+            max_ttl: Duration::from_secs(max_ttl_seconds),
+            generations: Vec::new(),
+        }
+    }
+
+    // Stop serving every entry once an event of T is emitted. Entries don't
+    // track which records they hold, so any change may affect any of them.
+    // Entries remember the emitters' generation from before they were
+    // computed, so a response that read data while a write was being made is
+    // never served after that write's event, whatever order the two finish in.
+    pub fn invalidate_on<T>(mut self, event_bus: &EventBus) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let emitter = event_bus.emitter::<T>();
+        self.generations.push(Arc::new(move || emitter.generation()));
+        self
+    }
+
+    fn generation(&self) -> u64 {
+        self.generations.iter().map(|generation| generation()).sum()
+    }
+
+    pub fn invalidate(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<Response> {
+        let mut entries = self.entries.lock().ok()?;
+        let entry = entries.get(key)?;
+        let remaining = entry.expires_at.checked_duration_since(Instant::now()).filter(|d| !d.is_zero());
+        let Some(remaining) = remaining.filter(|_| entry.generation == self.generation()) else {
+            entries.pop(key);
+            return None;
+        };
+
+        let mut response = Response::new(entry.data.clone());
+        // Clients are told how long the entry has left, at least a second
+        response.cache_control = CacheControl {
+            max_age: remaining.as_secs().max(1) as i32,
+            ..entry.cache_control
+        };
+        Some(response)
+    }
+
+    fn put(&self, key: String, response: &Response, generation: u64) {
+        let ttl = Duration::from_secs(response.cache_control.max_age.max(0) as u64).min(self.max_ttl);
+        if ttl.is_zero() {
+            return;
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.put(
+                key,
+                CachedResponse {
+                    data: response.data.clone(),
+                    cache_control: response.cache_control,
+                    expires_at: Instant::now() + ttl,
+                    generation,
+                },
+            );
+        }
+    }
+}
+
+impl ExtensionFactory for ResponseCache {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(ResponseCacheRequest { cache: self.clone(), state: Mutex::new(RequestState::default()) })
+    }
+}
+
+// What the hooks of one request learn about it, in the order they run
+#[derive(Default)]
+struct RequestState {
+    operation_name: Option<String>,
+    variables: String,
+    public_scope: String,
+    private_scope: Option<String>,
+    // Set by parse_query for query operations only
+    query_hash: Option<String>,
+    cache_control: CacheControl,
+}
+
+struct ResponseCacheRequest {
+    cache: ResponseCache,
+    state: Mutex<RequestState>,
+}
+
+impl ResponseCacheRequest {
+    fn key(&self) -> Option<String> {
+        let state = self.state.lock().ok()?;
+        let query_hash = state.query_hash.as_ref()?;
+        if state.cache_control.max_age <= 0 {
+            return None;
+        }
+        let scope = if state.cache_control.public {
+            &state.public_scope
+        } else {
+            state.private_scope.as_ref()?
+        };
+        Some(format!("{}:{}", scope, query_hash))
+    }
+}
+
+#[async_trait::async_trait]
+impl Extension for ResponseCacheRequest {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        let user = request_user(ctx, &request);
+        if let Ok(mut state) = self.state.lock() {
+            state.operation_name = request.operation_name.clone();
+            state.variables = canonical_variables(&request.variables);
+            state.public_scope = public_scope(user);
+            state.private_scope = user.map(|user| format!("user:{}", user.id));
+        }
+        next.run(ctx, request).await
+    }
+
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+        if let Ok(mut state) = self.state.lock() {
+            let operation = document.operations.iter().find(|(name, _)| match &state.operation_name {
+                Some(wanted) => name.is_some_and(|name| name.as_str() == wanted),
+                None => true,
+            });
+            // Whether introspection is allowed depends on the caller (admin,
+            // secret header), which the key doesn't capture
+            let cacheable = operation.is_some_and(|(_, operation)| {
+                operation.node.ty == OperationType::Query
+                    && !selects_introspection(&document, &operation.node.selection_set.node, &mut Vec::new())
+            });
+
+            if cacheable {
+                let mut hasher = Sha256::new();
+                hasher.update(normalize_query(query));
+                hasher.update([0]);
+                hasher.update(state.operation_name.as_deref().unwrap_or_default());
+                hasher.update([0]);
+                hasher.update(&state.variables);
+                state.query_hash = Some(format!("{:x}", hasher.finalize()));
+            }
+        }
+        Ok(document)
+    }
+
+    async fn validation(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextValidation<'_>,
+    ) -> Result<ValidationResult, Vec<ServerError>> {
+        let result = next.run(ctx).await?;
+        if let Ok(mut state) = self.state.lock() {
+            state.cache_control = result.cache_control;
+        }
+        Ok(result)
+    }
+
+    async fn execute(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        operation_name: Option<&str>,
+        next: NextExecute<'_>,
+    ) -> Response {
+        let Some(key) = self.key() else {
+            return next.run(ctx, operation_name).await;
+        };
+        if let Some(response) = self.cache.get(&key) {
+            return response;
+        }
+
+        let generation = self.cache.generation();
+        let response = next.run(ctx, operation_name).await;
+        if response.is_ok() {
+            self.cache.put(key, &response, generation);
+        }
+        response
+    }
+}
+
+// Whether `__schema` or `__type` is selected anywhere, fragments included.
+// Runs before validation, so fragment cycles are cut with `visited`.
+fn selects_introspection<'a>(
+    document: &'a ExecutableDocument,
+    selection_set: &'a SelectionSet,
+    visited: &mut Vec<&'a Name>,
+) -> bool {
+    selection_set.items.iter().any(|selection| match &selection.node {
+        Selection::Field(field) => {
+            let name = field.node.name.node.as_str();
+            name == "__schema"
+                || name == "__type"
+                || selects_introspection(document, &field.node.selection_set.node, visited)
+        }
+        Selection::InlineFragment(fragment) => {
+            selects_introspection(document, &fragment.node.selection_set.node, visited)
+        }
+        Selection::FragmentSpread(spread) => {
+            let name = &spread.node.fragment_name.node;
+            if visited.contains(&name) {
+                return false;
+            }
+            visited.push(name);
+            document
+                .fragments
+                .get(name)
+                .is_some_and(|fragment| selects_introspection(document, &fragment.node.selection_set.node, visited))
+        }
+    })
+}
+
+// Viewers sharing roles and permissions pass the same guards
+fn public_scope(user: Option<&CurrentUser>) -> String {
+    let Some(user) = user else {
+        return "anonymous".to_string();
+    };
+    let mut roles = user.roles.iter().map(|role| format!("{:?}", role)).collect::<Vec<_>>();
+    let mut permissions = user.permissions.iter().map(|permission| format!("{:?}", permission)).collect::<Vec<_>>();
+    roles.sort();
+    roles.dedup();
+    permissions.sort();
+    permissions.dedup();
+    format!("roles={};permissions={}", roles.join(","), permissions.join(","))
+}
+
+// Variables with object keys sorted, so key order doesn't split entries
+fn canonical_variables(variables: &Variables) -> String {
+    fn sorted(value: serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(map) => {
+                let mut entries = map.into_iter().collect::<Vec<_>>();
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                serde_json::Value::Object(entries.into_iter().map(|(key, value)| (key, sorted(value))).collect())
+            }
+            serde_json::Value::Array(items) => serde_json::Value::Array(items.into_iter().map(sorted).collect()),
+            value => value,
+        }
+    }
+
+    let value = variables.clone().into_value().into_json().unwrap_or_default();
+    sorted(value).to_string()
+}
+
+// Collapse insignificant whitespace, commas and comments outside string
+// literals, so formatting alone doesn't split entries
+fn normalize_query(query: &str) -> String {
+    let bytes = query.as_bytes();
+    let mut normalized = String::with_capacity(query.len());
+    let mut separator = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' && bytes[i] != b'\r' {
+                    i += 1;
+                }
+                separator = true;
+            }
+            b' ' | b'\t' | b'\n' | b'\r' | b',' => {
+                i += 1;
+                separator = true;
+            }
+            _ => {
+                if separator && !normalized.is_empty() {
+                    normalized.push(' ');
+                }
+                separator = false;
+
+                let end = if bytes[i] == b'"' {
+                    string_end(query, i)
+                } else {
+                    i + query[i..].chars().next().map_or(1, char::len_utf8)
+                };
+                normalized.push_str(&query[i..end]);
+                i = end;
+            }
+        }
+    }
+    normalized
+}
+
+// End of the string literal starting at `start`, block strings included
+fn string_end(query: &str, start: usize) -> usize {
+    let rest = &query[start..];
+    if rest.starts_with("\"\"\"") {
+        let mut from = 3;
+        while let Some(offset) = rest[from..].find("\"\"\"") {
+            let at = from + offset;
+            if !rest[..at].ends_with('\\') {
+                return start + at + 3;
+            }
+            from = at + 3;
+        }
+        return query.len();
+    }
+
+    let bytes = rest.as_bytes();
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return start + i + 1,
+            _ => i += 1,
+        }
+    }
+    query.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::parser::parse_query;
+
+    use super::*;
+    use crate::base::base_event::EventType;
+
+    fn introspective(query: &str) -> bool {
+        let document = parse_query(query).unwrap();
+        let (_, operation) = document.operations.iter().next().unwrap();
+        selects_introspection(&document, &operation.node.selection_set.node, &mut Vec::new())
+    }
+
+    #[test]
+    fn normalize_collapses_whitespace_commas_and_comments() {
+        let query = "query Users($first: Int,   $after: String) {\n  users(first: $first, after: $after) { # page\n    id\n  }\n}";
+        assert_eq!(
+            normalize_query(query),
+            "query Users($first: Int $after: String) { users(first: $first after: $after) { id } }"
+        );
+        assert_eq!(normalize_query("{ a }"), normalize_query("{\r\n\ta,,\n}  # trailing"));
+    }
+
+    #[test]
+    fn normalize_keeps_string_literals_intact() {
+        assert_eq!(
+            normalize_query(r#"{ user(name: "a,  b # c") { id } }"#),
+            r#"{ user(name: "a,  b # c") { id } }"#
+        );
+        assert_eq!(normalize_query(r#"{ a(s: "x \"  y") }"#), r#"{ a(s: "x \"  y") }"#);
+        assert_eq!(
+            normalize_query("{ a(s: \"\"\"line,\n  # not a comment \\\"\"\" \"\"\") }"),
+            "{ a(s: \"\"\"line,\n  # not a comment \\\"\"\" \"\"\") }"
+        );
+        assert_ne!(normalize_query(r#"{ a(s: "x  y") }"#), normalize_query(r#"{ a(s: "x y") }"#));
+    }
+
+    #[test]
+    fn normalize_handles_unterminated_strings_and_unicode() {
+        assert_eq!(normalize_query(r#"{ a(s: "open"#), r#"{ a(s: "open"#);
+        assert_eq!(normalize_query("{  héllo  }"), "{ héllo }");
+    }
+
+    #[test]
+    fn canonical_variables_ignore_key_order() {
+        let a = Variables::from_json(serde_json::json!({ "b": 1, "a": { "y": [1, { "d": 2, "c": 3 }], "x": null } }));
+        let b = Variables::from_json(serde_json::json!({ "a": { "x": null, "y": [1, { "c": 3, "d": 2 }] }, "b": 1 }));
+        assert_eq!(canonical_variables(&a), canonical_variables(&b));
+        assert_eq!(canonical_variables(&a), r#"{"a":{"x":null,"y":[1,{"c":3,"d":2}]},"b":1}"#);
+    }
+
+    #[test]
+    fn canonical_variables_keep_array_order() {
+        let a = Variables::from_json(serde_json::json!({ "ids": [1, 2] }));
+        let b = Variables::from_json(serde_json::json!({ "ids": [2, 1] }));
+        assert_ne!(canonical_variables(&a), canonical_variables(&b));
+    }
+
+    #[test]
+    fn introspection_is_found_through_fragments() {
+        assert!(!introspective("{ sayHello __typename }"));
+        assert!(introspective("{ sayHello __schema { queryType { name } } }"));
+        assert!(introspective(r#"{ __type(name: "User") { name } }"#));
+        assert!(introspective("{ ... on QueryRoot { __schema { types { name } } } }"));
+        assert!(introspective("{ ...F } fragment F on QueryRoot { me { id } ...G } fragment G on QueryRoot { s: __schema { description } }"));
+        assert!(!introspective("{ me { __type: id } }"));
+        assert!(!introspective("{ ...F } fragment F on QueryRoot { ...F sayHello }"));
+    }
+
+    #[test]
+    fn responses_computed_before_an_emit_are_not_served() {
+        let event_bus = EventBus::new();
+        let cache = ResponseCache::new(10, 60).invalidate_on::<u32>(&event_bus);
+        let mut response = Response::new(Value::Null);
+        response.cache_control = CacheControl { max_age: 60, ..Default::default() };
+
+        cache.put("before".to_string(), &response, cache.generation());
+        assert!(cache.get("before").is_some());
+
+        // A write's event is emitted while this response is being computed
+        let generation = cache.generation();
+        event_bus.emitter::<u32>().emit_local(EventType::Updated, 1);
+        cache.put("during".to_string(), &response, generation);
+
+        assert!(cache.get("before").is_none());
+        assert!(cache.get("during").is_none());
+
+        cache.put("after".to_string(), &response, cache.generation());
+        assert!(cache.get("after").is_some());
+    }
+}
@@ -1,49 +1,93 @@
-use async_graphql::{Schema, EmptySubscription, Object, Context};
-use mongodb::Database;
-use crate::graphql::modules::user::resolver::{UserQuery, UserMutation};
+use std::sync::Arc;
+
+use async_graphql::{MergedObject, MergedSubscription, Object, Schema};
+use mongodb::{Client, Database};
+use crate::base::base_event::EventBus;
+use crate::configs::get_config;
+use crate::constants::cache::RESPONSE_CACHE_CAPACITY;
+use crate::db::transaction::UnitOfWorkExtension;
+use crate::graphql::introspection::IntrospectionPolicy;
+use crate::graphql::limits::QueryLimits;
+use crate::graphql::loaders::LoadersExtension;
+use crate::graphql::persisted_queries::{query_store, PersistedQueries};
+use crate::graphql::response_cache::ResponseCache;
+use crate::graphql::modules::audit::resolver::AuditQuery;
+use crate::graphql::modules::auth::resolver::{AuthMutation, AuthQuery};
+use crate::graphql::modules::persisted_query::resolver::PersistedQueryMutation;
+use crate::graphql::modules::user::model::User;
+use crate::graphql::modules::user::resolver::{UserQuery, UserMutation, UserSubscription};
+use crate::helpers::jwt::JwtService;
 
 pub struct DbContext {
+    pub client: Client,
     pub db: Database,
 }
 
-// Root Query struct combining all module queries
+// Queries that don't belong to any module
 #[derive(Default)]
-pub struct QueryRoot;
+pub struct BaseQuery;
 
 #[Object]
-impl QueryRoot {
+impl BaseQuery {
+    #[graphql(cache_control(max_age = 3600))]
     async fn say_hello(&self) -> &str {
         "Hello, Rust GraphQL!"
     }
-    
-    // Delegate user queries to the UserQuery resolver
-    async fn users(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<crate::graphql::modules::user::model::User>> {
-        UserQuery::default().users(ctx).await.map_err(|e| e.into())
-    }
-    
-    async fn user(&self, ctx: &Context<'_>, id: async_graphql::ID) -> Result<Option<crate::graphql::modules::user::model::User>, async_graphql::Error> {
-        let result = UserQuery::default().user(ctx, id).await?;
-        Ok(result)
-    }
 }
 
-// Root Mutation struct combining all module mutations
-#[derive(Default)]
-pub struct MutationRoot;
+// Root Query combining all module queries. Module resolvers are merged
+// rather than delegated to so that their guards are enforced.
+#[derive(MergedObject, Default)]
+pub struct QueryRoot(BaseQuery, UserQuery, AuthQuery, AuditQuery);
 
-#[Object]
-impl MutationRoot {
-    // Delegate user mutations to the UserMutation resolver
-    async fn create_user(&self, ctx: &Context<'_>, name: String, email: String, age: Option<i32>) -> Result<crate::graphql::modules::user::model::User, String> {
-        UserMutation::default().create_user(ctx, name, email, age).await
-    }
-}
+// Root Mutation combining all module mutations
+#[derive(MergedObject, Default)]
+pub struct MutationRoot(UserMutation, AuthMutation, PersistedQueryMutation);
+
+// Root Subscription combining all module subscriptions
+#[derive(MergedSubscription, Default)]
+pub struct SubscriptionRoot(UserSubscription);
 
-pub type AppSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+pub type AppSchema = Schema<QueryRoot, MutationRoot, SubscriptionRoot>;
 
-pub fn build_schema(db: Database) -> AppSchema {
-    let db_ctx = DbContext { db };
-    Schema::build(QueryRoot::default(), MutationRoot::default(), EmptySubscription)
-        .data(db_ctx)
+// The event bus is shared with the rest of the application so that services
+// can publish and resolvers/subscriptions can listen through the context
+pub fn build_schema(client: Client, db: Database, event_bus: Arc<EventBus>, jwt: Arc<JwtService>) -> AppSchema {
+    let config = get_config();
+    let limits = QueryLimits::from_config(config);
+    let mut builder = Schema::build(QueryRoot::default(), MutationRoot::default(), SubscriptionRoot::default())
+        .limit_recursive_depth(limits.max_recursion())
+        .extension(limits)
+        .extension(LoadersExtension::new(db.clone()));
+    
+    // Mỗi request chạy trong một transaction, xem db::transaction
+    if config.unit_of_work_enabled() {
+        builder = builder.extension(UnitOfWorkExtension::new(client.clone()));
+    }
+    
+    // Introspection chỉ dành cho admin hoặc request có secret, xem graphql::introspection
+    if !config.introspection_enabled() {
+        builder = builder.extension(IntrospectionPolicy);
+    }
+    
+    // Automatic persisted queries, xem graphql::persisted_queries
+    let store = query_store(config, &db).expect("Cấu hình persisted query không hợp lệ");
+    if let Some(store) = store {
+        builder = builder
+            .extension(PersistedQueries::new(store.clone(), config.apq_allowlist_only()))
+            .data(store);
+    }
+    
+    // Cache response của query theo `cache_control`, xoá khi user thay đổi
+    if config.cache_enabled() {
+        let cache = ResponseCache::new(RESPONSE_CACHE_CAPACITY, config.cache_expiration())
+            .invalidate_on::<User>(&event_bus);
+        builder = builder.extension(cache);
+    }
+    
+    builder
+        .data(DbContext { client, db })
+        .data(event_bus)
+        .data(jwt)
         .finish()
 }
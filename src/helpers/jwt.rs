@@ -0,0 +1,228 @@
+//! Phát hành và giải mã JWT (HS256 hoặc RS256)
+
+use std::fs;
+
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::base::auth::{Permission, Role};
+use crate::base::error::{ServiceError, ServiceResult};
+use crate::configs::config::AppConfig;
+use crate::constants::jwt::DEV_JWT_SECRET;
+
+/// Loại token, để refresh token không thể dùng thay access token và ngược lại
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenType {
+    Access,
+    Refresh,
+}
+
+/// Claims được ký trong token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    /// Id của user
+    pub sub: String,
+    /// Id duy nhất của token, dùng để thu hồi refresh token
+    pub jti: String,
+    pub iat: i64,
+    pub exp: i64,
+    pub token_type: TokenType,
+    /// Role và quyền của user tại thời điểm phát hành, chỉ có trong access token
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<Role>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permissions: Vec<Permission>,
+}
+
+/// Token đã được ký cùng với claims của nó
+pub struct IssuedToken {
+    pub token: String,
+    pub claims: Claims,
+}
+
+/// Service ký và xác thực JWT theo cấu hình ứng dụng
+pub struct JwtService {
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    access_token_ttl: i64,
+    refresh_token_ttl: i64,
+}
+
+impl JwtService {
+    /// Tạo service từ cấu hình, RS256 đọc cặp khoá PEM từ đường dẫn đã cấu hình.
+    /// Ở production, HS256 bắt buộc phải có `JWT_SECRET` riêng, nếu không ai cũng ký được token admin
+    pub fn from_config(config: &AppConfig) -> ServiceResult<Self> {
+        let base = config.base();
+        let (algorithm, encoding_key, decoding_key) = match base.jwt_algorithm.to_uppercase().as_str() {
+            "HS256" if config.is_production() && base.jwt_secret == DEV_JWT_SECRET => {
+                return Err(ServiceError::InternalServerError(
+                    "JWT_SECRET must be set to a non-default value in production".to_string(),
+                ))
+            }
+            "HS256" => (
+                Algorithm::HS256,
+                EncodingKey::from_secret(base.jwt_secret.as_bytes()),
+                DecodingKey::from_secret(base.jwt_secret.as_bytes()),
+            ),
+            "RS256" => {
+                let private_key = read_key(base.jwt_private_key_path.as_deref(), "JWT_PRIVATE_KEY_PATH")?;
+                let public_key = read_key(base.jwt_public_key_path.as_deref(), "JWT_PUBLIC_KEY_PATH")?;
+                (
+                    Algorithm::RS256,
+                    EncodingKey::from_rsa_pem(&private_key)
+                        .map_err(|e| ServiceError::InternalServerError(e.to_string()))?,
+                    DecodingKey::from_rsa_pem(&public_key)
+                        .map_err(|e| ServiceError::InternalServerError(e.to_string()))?,
+                )
+            }
+            other => {
+                return Err(ServiceError::InternalServerError(format!(
+                    "Unsupported JWT algorithm: {}",
+                    other
+                )))
+            }
+        };
+
+        Ok(Self {
+            algorithm,
+            encoding_key,
+            decoding_key,
+            access_token_ttl: base.access_token_ttl as i64,
+            refresh_token_ttl: base.refresh_token_ttl as i64,
+        })
+    }
+
+    /// Thời gian sống của access token (seconds)
+    pub fn access_token_ttl(&self) -> i64 {
+        self.access_token_ttl
+    }
+
+    pub fn issue_access_token(
+        &self,
+        user_id: &str,
+        roles: &[Role],
+        permissions: &[Permission],
+    ) -> ServiceResult<IssuedToken> {
+        self.issue(user_id, TokenType::Access, self.access_token_ttl, roles, permissions)
+    }
+
+    pub fn issue_refresh_token(&self, user_id: &str) -> ServiceResult<IssuedToken> {
+        self.issue(user_id, TokenType::Refresh, self.refresh_token_ttl, &[], &[])
+    }
+
+    fn issue(
+        &self,
+        user_id: &str,
+        token_type: TokenType,
+        ttl: i64,
+        roles: &[Role],
+        permissions: &[Permission],
+    ) -> ServiceResult<IssuedToken> {
+        let now = mongodb::bson::DateTime::now().timestamp_millis() / 1000;
+        let claims = Claims {
+            sub: user_id.to_string(),
+            jti: ObjectId::new().to_hex(),
+            iat: now,
+            exp: now + ttl,
+            token_type,
+            roles: roles.to_vec(),
+            permissions: permissions.to_vec(),
+        };
+        let token = encode(&Header::new(self.algorithm), &claims, &self.encoding_key)
+            .map_err(|e| ServiceError::InternalServerError(e.to_string()))?;
+        Ok(IssuedToken { token, claims })
+    }
+
+    /// Giải mã và kiểm tra chữ ký, hạn dùng và loại token
+    pub fn decode(&self, token: &str, expected: TokenType) -> ServiceResult<Claims> {
+        let validation = Validation::new(self.algorithm);
+        let claims = decode::<Claims>(token, &self.decoding_key, &validation)
+            .map_err(|e| ServiceError::AuthenticationError(format!("Invalid token: {}", e)))?
+            .claims;
+
+        if claims.token_type != expected {
+            return Err(ServiceError::AuthenticationError("Invalid token type".to_string()));
+        }
+        Ok(claims)
+    }
+}
+
+fn read_key(path: Option<&str>, variable: &str) -> ServiceResult<Vec<u8>> {
+    let path = path.ok_or_else(|| {
+        ServiceError::InternalServerError(format!("{} must be set for RS256", variable))
+    })?;
+    fs::read(path).map_err(|e| ServiceError::InternalServerError(format!("Cannot read {}: {}", path, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::base::BaseConfig;
+    use crate::configs::development::DevelopmentConfig;
+    use crate::configs::production::ProductionConfig;
+
+    fn base(secret: &str) -> BaseConfig {
+        BaseConfig {
+            jwt_algorithm: "HS256".to_string(),
+            jwt_secret: secret.to_string(),
+            ..BaseConfig::default()
+        }
+    }
+
+    fn production(secret: &str) -> AppConfig {
+        AppConfig::Production(ProductionConfig { base: base(secret), enable_cache: false, cache_expiration: 0 })
+    }
+
+    fn service() -> JwtService {
+        JwtService::from_config(&production("test-secret")).unwrap()
+    }
+
+    #[test]
+    fn tokens_round_trip_their_claims() {
+        let jwt = service();
+        let issued = jwt.issue_access_token("user-1", &[Role::Moderator], &[Permission::UsersDelete]).unwrap();
+
+        let claims = jwt.decode(&issued.token, TokenType::Access).unwrap();
+        assert_eq!(claims.sub, "user-1");
+        assert_eq!(claims.roles, vec![Role::Moderator]);
+        assert_eq!(claims.permissions, vec![Permission::UsersDelete]);
+        assert_eq!(claims.exp - claims.iat, jwt.access_token_ttl());
+    }
+
+    #[test]
+    fn token_types_cannot_be_swapped() {
+        let jwt = service();
+        let refresh = jwt.issue_refresh_token("user-1").unwrap();
+        let access = jwt.issue_access_token("user-1", &[Role::Admin], &[]).unwrap();
+
+        assert!(matches!(jwt.decode(&refresh.token, TokenType::Access), Err(ServiceError::AuthenticationError(_))));
+        assert!(matches!(jwt.decode(&access.token, TokenType::Refresh), Err(ServiceError::AuthenticationError(_))));
+        assert!(jwt.decode(&refresh.token, TokenType::Refresh).is_ok());
+    }
+
+    #[test]
+    fn expired_and_foreign_tokens_are_rejected() {
+        let jwt = service();
+        // Quá hạn lâu hơn leeway mặc định 60 giây của jsonwebtoken
+        let expired = jwt.issue("user-1", TokenType::Access, -120, &[], &[]).unwrap();
+        assert!(matches!(jwt.decode(&expired.token, TokenType::Access), Err(ServiceError::AuthenticationError(_))));
+
+        let other = JwtService::from_config(&production("other-secret")).unwrap();
+        let foreign = other.issue_access_token("user-1", &[Role::Admin], &[]).unwrap();
+        assert!(jwt.decode(&foreign.token, TokenType::Access).is_err());
+    }
+
+    #[test]
+    fn production_refuses_the_default_secret() {
+        assert!(matches!(
+            JwtService::from_config(&production(DEV_JWT_SECRET)),
+            Err(ServiceError::InternalServerError(_))
+        ));
+
+        let development = AppConfig::Development(DevelopmentConfig { base: base(DEV_JWT_SECRET), debug: true });
+        assert!(JwtService::from_config(&development).is_ok());
+    }
+}
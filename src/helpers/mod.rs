@@ -1 +1,3 @@
 // Export các thành phần trong module helpers
+pub mod jwt;
+pub mod password;
@@ -0,0 +1,31 @@
+//! Băm và kiểm tra mật khẩu bằng Argon2id
+
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use std::sync::OnceLock;
+
+use argon2::Argon2;
+
+use crate::base::error::{ServiceError, ServiceResult};
+
+/// Băm mật khẩu với salt ngẫu nhiên, kết quả ở định dạng PHC string
+pub fn hash_password(password: &str) -> ServiceResult<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| ServiceError::InternalServerError(e.to_string()))
+}
+
+/// Kiểm tra mật khẩu với hash đã lưu, hash hỏng được coi là không khớp
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
+
+/// Hash dùng để kiểm tra mật khẩu khi không có user nào khớp, để đăng nhập bằng email
+/// không tồn tại tốn thời gian như khi sai mật khẩu
+pub fn dummy_password_hash() -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| hash_password("dummy password").unwrap_or_default())
+}
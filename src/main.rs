@@ -1,26 +1,27 @@
-use axum::{routing::get, Router, response::Html, Extension};
-use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use dotenv::dotenv;
 
-// Import base module from the library crate
-extern crate graphql_rust;
-
-mod db;
-mod graphql;
-mod configs;
-
-use db::connection;
-use graphql::schema::{build_schema, AppSchema};
-use configs::get_config;
-
-async fn graphql_handler(schema: Extension<AppSchema>, req: GraphQLRequest) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
-}
-
-async fn graphiql() -> Html<String> {
-    Html(async_graphql::http::GraphiQLSource::build().endpoint("/graphql").finish())
-}
+// Import các module từ library crate
+use graphql_rust::base::audit::AuditLog;
+use graphql_rust::base::base_event::EventBus;
+use graphql_rust::base::health::{Health, MongoHealthCheck};
+use graphql_rust::base::shutdown::{termination_signal, Shutdown};
+use graphql_rust::db::change_stream::ChangeStreamWatcher;
+use graphql_rust::db::connection;
+use graphql_rust::db::indexes::IndexRegistry;
+use graphql_rust::graphql::modules::auth::model::RefreshTokenRecord;
+use graphql_rust::graphql::modules::user::model::User;
+use graphql_rust::graphql::persisted_queries::PersistedQueryRecord;
+use graphql_rust::graphql::schema::build_schema;
+use graphql_rust::configs::get_config;
+use graphql_rust::constants::audit::AUDIT_COLLECTION;
+use graphql_rust::constants::health::HEALTH_CHECK_TIMEOUT_MS;
+use graphql_rust::constants::persisted_query::PERSISTED_QUERY_COLLECTION;
+use graphql_rust::constants::shutdown::MONGO_SHUTDOWN_TIMEOUT_SECONDS;
+use graphql_rust::helpers::jwt::JwtService;
+use graphql_rust::routers;
 
 #[tokio::main]
 async fn main() {
@@ -28,24 +29,112 @@ async fn main() {
     dotenv().ok();
     
     // Kết nối đến MongoDB
-    let db = connection::connect_db().await;
+    let client = connection::connect_client().await;
+    let db = client.database(get_config().mongo_db());
     
     // Kiểm tra kết nối
     let _ = connection::test_connection(&db).await;
     
-    // Xây dựng schema GraphQL với kết nối MongoDB
-    let schema = build_schema(db);
+    // Tạo các index khai báo trên model (unique email, TTL refresh token và persisted query, ...).
+    // Không khởi động khi thiếu index: unique index là thứ chặn email trùng.
+    IndexRegistry::new()
+        .register::<User>("users")
+        .register::<RefreshTokenRecord>("refresh_tokens")
+        .register::<AuditLog>(AUDIT_COLLECTION)
+        .register::<PersistedQueryRecord>(PERSISTED_QUERY_COLLECTION)
+        .ensure(&db)
+        .await
+        .expect("Không thể tạo index MongoDB");
+    
+    // Event bus dùng chung cho services, resolvers và subscriptions
+    let event_bus = Arc::new(EventBus::new());
     
     // Lấy cấu hình từ singleton config
     let config = get_config();
     
-    let app = Router::new()
-        .route("/graphql", get(graphiql).post(graphql_handler))
-        .layer(Extension(schema));
+    // Service ký và xác thực JWT
+    let jwt = Arc::new(JwtService::from_config(config).expect("Không thể khởi tạo JWT service"));
+    
+    // Phát lại các thay đổi từ MongoDB change stream (dùng khi chạy nhiều instance)
+    let change_streams = if config.change_streams_enabled() {
+        ChangeStreamWatcher::new(db.clone(), event_bus.clone())
+            .register::<User>("users")
+            .spawn(config.change_stream_collections())
+    } else {
+        Vec::new()
+    };
+    
+    // Readiness kiểm tra MongoDB, chuyển sang not-ready khi đang tắt server
+    let health = Health::new(Duration::from_millis(HEALTH_CHECK_TIMEOUT_MS))
+        .with_check(MongoHealthCheck::new(db.clone()));
+    
+    // Xây dựng schema GraphQL với kết nối MongoDB
+    let schema = build_schema(client.clone(), db, event_bus, jwt.clone());
+    
+    // Tín hiệu tắt server dùng chung cho HTTP server và các WebSocket
+    let shutdown = Shutdown::new();
+    
+    // Toàn bộ route HTTP được đăng ký tại routers::app
+    let app = routers::app(schema, jwt, health.clone(), shutdown.clone());
 
     // Sử dụng host và port từ cấu hình
     let addr = format!("{}:{}", config.host(), config.port());
     let listener = TcpListener::bind(&addr).await.unwrap();
     println!("🚀 GraphQL server running at http://{}:{}/graphql", config.host(), config.port());
-    axum::serve(listener, app).await.unwrap();
+    println!("🔌 Subscriptions available at ws://{}:{}/ws", config.host(), config.port());
+    println!("💓 Health checks at http://{}:{}/health/live and /health/ready", config.host(), config.port());
+    
+    // SIGTERM/SIGINT: báo not-ready, chờ load balancer ngừng gửi request (gửi tín hiệu
+    // lần nữa để bỏ qua), rồi ngừng nhận kết nối mới và đóng các subscription
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            termination_signal().await;
+            health.begin_shutdown();
+            if config.shutdown_delay() > 0 {
+                println!("🛑 Đã báo not-ready, ngừng nhận kết nối sau {} giây...", config.shutdown_delay());
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(config.shutdown_delay())) => {}
+                    _ = termination_signal() => {}
+                }
+            }
+            println!("🛑 Đang tắt server, chờ tối đa {} giây cho các kết nối đang mở...", config.shutdown_timeout());
+            shutdown.trigger();
+        }
+    });
+    
+    // Server trả về sau khi các request HTTP đang chạy kết thúc, sau đó chờ các WebSocket đóng
+    let server = axum::serve(listener, app).with_graceful_shutdown({
+        let shutdown = shutdown.clone();
+        async move { shutdown.triggered().await }
+    });
+    let drained = async {
+        let result = server.await;
+        shutdown.drained().await;
+        result
+    };
+    let deadline = async {
+        shutdown.triggered().await;
+        tokio::time::sleep(Duration::from_secs(config.shutdown_timeout())).await;
+    };
+    tokio::select! {
+        result = drained => {
+            if let Err(e) = result {
+                eprintln!("Lỗi server: {}", e);
+            }
+        }
+        _ = deadline => {
+            eprintln!("Hết thời gian chờ, đóng {} kết nối còn lại", shutdown.active_connections());
+        }
+    }
+    
+    // Dừng change stream để giải phóng cursor, rồi đóng kết nối MongoDB
+    for task in change_streams {
+        task.abort();
+    }
+    let mongo_timeout = Duration::from_secs(MONGO_SHUTDOWN_TIMEOUT_SECONDS);
+    if tokio::time::timeout(mongo_timeout, client.clone().shutdown()).await.is_err() {
+        client.shutdown_immediate().await;
+    }
+    println!("👋 Server đã dừng");
 }
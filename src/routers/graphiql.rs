@@ -0,0 +1,25 @@
+use axum::response::Html;
+use axum::routing::get;
+
+use crate::base::base_router::{BaseRouter, HttpRouter};
+use crate::configs::get_config;
+
+// GraphiQL IDE on GET /graphql, off by default in production
+pub struct GraphiQLRouter;
+
+impl BaseRouter for GraphiQLRouter {
+    fn routes(&self) -> HttpRouter {
+        HttpRouter::new().route("/graphql", get(graphiql))
+    }
+
+    fn enabled(&self) -> bool {
+        get_config().graphiql_enabled()
+    }
+}
+
+async fn graphiql() -> Html<String> {
+    Html(async_graphql::http::GraphiQLSource::build()
+        .endpoint("/graphql")
+        .subscription_endpoint("/ws")
+        .finish())
+}
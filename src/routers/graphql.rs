@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use async_graphql::Data;
+use async_graphql_axum::{GraphQLProtocol, GraphQLRequest, GraphQLResponse, GraphQLWebSocket};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
+use axum::extract::WebSocketUpgrade;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Extension;
+use futures::{SinkExt, StreamExt};
+
+use crate::base::audit::RequestId;
+use crate::base::auth::{BearerAuth, CurrentUser};
+use crate::base::base_router::{BaseRouter, HttpRouter};
+use crate::base::shutdown::Shutdown;
+use crate::graphql::introspection::IntrospectionHeader;
+use crate::graphql::schema::AppSchema;
+use crate::helpers::jwt::JwtService;
+
+// POST /graphql for queries and mutations, /ws for subscriptions. Open
+// sockets are tracked and closed when the server shuts down.
+pub struct GraphQLRouter {
+    shutdown: Shutdown,
+}
+
+impl GraphQLRouter {
+    pub fn new(shutdown: Shutdown) -> Self {
+        Self { shutdown }
+    }
+}
+
+impl BaseRouter for GraphQLRouter {
+    fn routes(&self) -> HttpRouter {
+        HttpRouter::new()
+            .route("/graphql", post(graphql_handler))
+            .route("/ws", get(graphql_ws_handler))
+            .layer(Extension(self.shutdown.clone()))
+    }
+}
+
+async fn graphql_handler(
+    schema: Extension<AppSchema>,
+    BearerAuth(current_user): BearerAuth,
+    request_id: RequestId,
+    IntrospectionHeader(introspection_secret): IntrospectionHeader,
+    req: GraphQLRequest,
+) -> GraphQLResponse {
+    let mut req = req.into_inner().data(request_id);
+    if let Some(current_user) = current_user {
+        req = req.data(current_user);
+    }
+    if let Some(introspection_secret) = introspection_secret {
+        req = req.data(introspection_secret);
+    }
+    schema.execute(req).await.into()
+}
+
+// WebSocket cho subscriptions, hỗ trợ cả graphql-ws và graphql-transport-ws.
+// Token được gửi trong payload của connection_init: { "Authorization": "Bearer <token>" }
+async fn graphql_ws_handler(
+    Extension(schema): Extension<AppSchema>,
+    Extension(jwt): Extension<Arc<JwtService>>,
+    Extension(shutdown): Extension<Shutdown>,
+    protocol: GraphQLProtocol,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    if shutdown.is_triggered() {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+
+    upgrade
+        .protocols(async_graphql::http::ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |socket| serve_socket(socket, schema, jwt, shutdown, protocol))
+        .into_response()
+}
+
+async fn serve_socket(socket: WebSocket, schema: AppSchema, jwt: Arc<JwtService>, shutdown: Shutdown, protocol: GraphQLProtocol) {
+    let _guard = shutdown.track();
+    let (mut sink, stream) = socket.split();
+
+    // Stop reading on shutdown: the GraphQL session then ends its subscriptions
+    let stopped = shutdown.clone();
+    let stream = stream.take_until(async move { stopped.triggered().await });
+
+    GraphQLWebSocket::new_with_pair(&mut sink, stream, schema, protocol)
+        .on_connection_init(move |payload| async move {
+            let mut data = Data::default();
+            let header = payload
+                .get("Authorization")
+                .or_else(|| payload.get("authorization"))
+                .and_then(|value| value.as_str());
+            if let Some(header) = header {
+                data.insert(CurrentUser::from_bearer(&jwt, header)?);
+            }
+            Ok(data)
+        })
+        .serve()
+        .await;
+
+    // Tell the client to reconnect elsewhere rather than dropping the socket
+    if shutdown.is_triggered() {
+        let close = CloseFrame { code: close_code::AWAY, reason: "Server shutting down".into() };
+        let _ = sink.send(Message::Close(Some(close))).await;
+    }
+}
@@ -0,0 +1,39 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Extension, Json};
+
+use crate::base::base_router::{BaseRouter, HttpRouter};
+use crate::base::health::Health;
+
+// GET /health/live answers as long as the process serves requests, for
+// restart decisions. GET /health/ready checks the dependencies and answers 503
+// while any is down or the app is shutting down, for routing decisions.
+pub struct HealthCheckRouter {
+    health: Health,
+}
+
+impl HealthCheckRouter {
+    pub fn new(health: Health) -> Self {
+        Self { health }
+    }
+}
+
+impl BaseRouter for HealthCheckRouter {
+    fn routes(&self) -> HttpRouter {
+        HttpRouter::new()
+            .route("/health/live", get(live_handler))
+            .route("/health/ready", get(ready_handler))
+            .layer(Extension(self.health.clone()))
+    }
+}
+
+async fn live_handler() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+async fn ready_handler(Extension(health): Extension<Health>) -> Response {
+    let report = health.readiness().await;
+    let status = if report.is_ready() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(report)).into_response()
+}
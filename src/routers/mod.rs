@@ -1 +1,29 @@
 // Export các thành phần trong module routers
+pub mod graphiql;
+pub mod graphql;
+pub mod health;
+
+use std::sync::Arc;
+
+use axum::Extension;
+
+use crate::base::base_router::{HttpRouter, RouterRegistry};
+use crate::base::health::Health;
+use crate::base::shutdown::Shutdown;
+use crate::graphql::schema::AppSchema;
+use crate::helpers::jwt::JwtService;
+use crate::routers::graphiql::GraphiQLRouter;
+use crate::routers::graphql::GraphQLRouter;
+use crate::routers::health::HealthCheckRouter;
+
+// Every HTTP route of the app. A module exposing routes registers its router
+// here; the shared state is available to all of them as extensions.
+pub fn app(schema: AppSchema, jwt: Arc<JwtService>, health: Health, shutdown: Shutdown) -> HttpRouter {
+    RouterRegistry::new()
+        .register(GraphQLRouter::new(shutdown))
+        .register(GraphiQLRouter)
+        .register(HealthCheckRouter::new(health))
+        .into_router()
+        .layer(Extension(schema))
+        .layer(Extension(jwt))
+}